@@ -0,0 +1,52 @@
+use std::{hint::black_box, thread, time::Instant};
+
+use aoc_2022::{
+    days::one::{par_top_k, top_k, DAY_ONE},
+    random::Rng,
+};
+
+const ELVES: usize = 4_000_000;
+
+fn bench(name: &str, f: impl Fn() -> String) -> String {
+    const RUNS: u32 = 3;
+    let start = Instant::now();
+    let mut answer = String::new();
+    for _ in 0..RUNS {
+        answer = black_box(f());
+    }
+    println!("{:<24} {:>10.2?}", name, start.elapsed() / RUNS);
+    answer
+}
+
+fn sum(input: &str, k: usize, threads: Option<usize>) -> String {
+    let elves = match threads {
+        Some(threads) => par_top_k(input.as_bytes(), k, threads),
+        None => top_k(input.as_bytes(), k),
+    };
+    elves
+        .unwrap()
+        .iter()
+        .map(|elf| elf.calories)
+        .sum::<u64>()
+        .to_string()
+}
+
+fn main() {
+    let input = (DAY_ONE.generate)(&mut Rng::new(1), ELVES);
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    println!("{} MiB, {} threads", input.len() >> 20, threads);
+
+    let expected = bench("part one", || (DAY_ONE.part_one)(&input).to_string());
+    assert_eq!(expected, bench("streaming top 1", || sum(&input, 1, None)));
+    assert_eq!(
+        expected,
+        bench("parallel top 1", || sum(&input, 1, Some(threads)))
+    );
+
+    let expected = bench("part two", || (DAY_ONE.part_two)(&input).to_string());
+    assert_eq!(expected, bench("streaming top 3", || sum(&input, 3, None)));
+    assert_eq!(
+        expected,
+        bench("parallel top 3", || sum(&input, 3, Some(threads)))
+    );
+}
@@ -0,0 +1,53 @@
+use std::{collections::VecDeque, hint::black_box, time::Instant};
+
+use aoc_2022::{
+    days::six::{find_marker, find_marker_lowercase, find_marker_str, generate_datastream},
+    random::Rng,
+};
+use itertools::Itertools;
+
+const WINDOW: usize = 14;
+const INPUT_LEN: usize = 8 * 1024 * 1024;
+
+/// The original `VecDeque<char>` + `all_unique` scan, kept as a baseline.
+fn vec_deque_marker(input: &str, window: usize) -> Option<usize> {
+    let mut buf = input.chars().take(window).collect::<VecDeque<_>>();
+    let mut total_taken = window;
+    let mut chars = input.chars().skip(window);
+    while !buf.iter().all_unique() {
+        buf.pop_front();
+        buf.push_back(chars.next()?);
+        total_taken += 1;
+    }
+    Some(total_taken)
+}
+
+fn bench(name: &str, expected: usize, f: impl Fn() -> Option<usize>) {
+    const RUNS: u32 = 5;
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        assert_eq!(black_box(f()), Some(expected), "{}", name);
+    }
+    let per_run = start.elapsed() / RUNS;
+    let throughput = INPUT_LEN as f64 / per_run.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{:<24} {:>10.2?} {:>10.1} MiB/s", name, per_run, throughput);
+}
+
+fn main() {
+    // The only marker ends the datastream, so every scan reads all of it.
+    let input = generate_datastream(&mut Rng::new(0x2022_0006), INPUT_LEN, WINDOW, INPUT_LEN);
+    let expected = INPUT_LEN;
+
+    bench("VecDeque + all_unique", expected, || {
+        vec_deque_marker(&input, WINDOW)
+    });
+    bench("last-seen table", expected, || {
+        find_marker(input.as_bytes(), WINDOW)
+    });
+    bench("bitmask", expected, || {
+        find_marker_lowercase(input.as_bytes(), WINDOW)
+    });
+    bench("find_marker_str", expected, || {
+        find_marker_str(&input, WINDOW)
+    });
+}
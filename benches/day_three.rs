@@ -0,0 +1,63 @@
+use std::{collections::HashSet, hint::black_box, time::Instant};
+
+use aoc_2022::{
+    answer::Answer,
+    days::three::{Priority, DAY_THREE},
+    random::Rng,
+};
+use itertools::Itertools;
+
+const LINES: usize = 999_999;
+
+/// The original `HashSet<char>` implementation of part one, kept as a baseline.
+fn hash_set_part_one(input: &str) -> Answer {
+    input
+        .lines()
+        .map(|line| line.split_at(line.len() / 2))
+        .map(|(a, b)| {
+            let a = a.chars().collect::<HashSet<char>>();
+            let b = b.chars().collect::<HashSet<char>>();
+            a.intersection(&b)
+                .map(|c| c.priority().unwrap())
+                .sum::<i32>()
+        })
+        .sum::<i32>()
+        .into()
+}
+
+/// The original `HashSet<char>` implementation of part two, kept as a baseline.
+fn hash_set_part_two(input: &str) -> Answer {
+    input
+        .lines()
+        .map(|line| line.chars().collect::<HashSet<char>>())
+        .chunks(3)
+        .into_iter()
+        .flat_map(|chunk| chunk.reduce(|a, b| a.intersection(&b).cloned().collect()))
+        .flatten()
+        .map(|c| c.priority().unwrap())
+        .sum::<i32>()
+        .into()
+}
+
+fn bench(name: &str, input: &str, f: fn(&str) -> Answer) -> Answer {
+    const RUNS: u32 = 3;
+    let start = Instant::now();
+    let mut answer = black_box(f(input));
+    for _ in 1..RUNS {
+        answer = black_box(f(input));
+    }
+    println!("{:<24} {:>10.2?}", name, start.elapsed() / RUNS);
+    answer
+}
+
+fn main() {
+    let input = (DAY_THREE.generate)(&mut Rng::new(3), LINES);
+
+    let baseline = bench("HashSet part one", &input, hash_set_part_one);
+    let bitset = bench("bitset part one", &input, DAY_THREE.part_one);
+    assert_eq!(baseline, bitset);
+
+    let baseline = bench("HashSet part two", &input, hash_set_part_two);
+    let bitset = bench("bitset part two", &input, DAY_THREE.part_two);
+    assert_eq!(baseline, bitset);
+}
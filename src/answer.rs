@@ -0,0 +1,147 @@
+use std::{
+    convert::Infallible,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+
+/// The answer to one part of a puzzle.
+///
+/// Numeric answers compare by value, so an `Integer` and an `Unsigned` holding
+/// the same number are equal.
+#[derive(Debug, Clone, Eq)]
+pub enum Answer {
+    Integer(i64),
+    Unsigned(u64),
+    Text(String),
+    /// Several lines of text, like letters drawn on a screen.
+    Grid(Vec<String>),
+}
+
+impl Answer {
+    /// Where the expected answer for an input file is stored: next to it,
+    /// with an `answer` extension.
+    pub fn expected_path(input: impl AsRef<Path>) -> PathBuf {
+        input.as_ref().with_extension("answer")
+    }
+
+    /// Reads an expected answer stored in a file, see `FromStr`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to load expected answer {}", path.display()))?;
+        Ok(text.parse().unwrap())
+    }
+
+    /// The value of a numeric answer.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Answer::Integer(value) => Some(*value as i128),
+            Answer::Unsigned(value) => Some(*value as i128),
+            Answer::Text(_) | Answer::Grid(_) => None,
+        }
+    }
+}
+
+impl PartialEq for Answer {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Answer::Text(a), Answer::Text(b)) => a == b,
+            (Answer::Grid(a), Answer::Grid(b)) => a == b,
+            _ => self.as_i128().is_some() && self.as_i128() == other.as_i128(),
+        }
+    }
+}
+
+impl Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Answer::Integer(value) => write!(f, "{}", value),
+            Answer::Unsigned(value) => write!(f, "{}", value),
+            Answer::Text(text) => write!(f, "{}", text),
+            Answer::Grid(rows) => write!(f, "{}", rows.join("\n")),
+        }
+    }
+}
+
+/// Parses the way answers are stored: a number, a single line of text, or
+/// several lines for a grid. Trailing newlines are ignored.
+impl FromStr for Answer {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_end_matches(['\r', '\n']);
+        if s.contains('\n') {
+            return Ok(Answer::Grid(
+                s.lines().map(|line| line.trim_end().to_string()).collect(),
+            ));
+        }
+
+        let trimmed = s.trim();
+        if let Ok(value) = trimmed.parse() {
+            Ok(Answer::Unsigned(value))
+        } else if let Ok(value) = trimmed.parse() {
+            Ok(Answer::Integer(value))
+        } else {
+            Ok(Answer::Text(s.to_string()))
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($variant:ident as $inner:ty: $($t:ty),*) => {
+        $(
+            impl From<$t> for Answer {
+                fn from(value: $t) -> Self {
+                    Answer::$variant(value as $inner)
+                }
+            }
+        )*
+    };
+}
+
+impl_from!(Integer as i64: i8, i16, i32, i64, isize);
+impl_from!(Unsigned as u64: u8, u16, u32, u64, usize);
+
+impl From<String> for Answer {
+    fn from(text: String) -> Self {
+        Answer::Text(text)
+    }
+}
+
+impl From<&str> for Answer {
+    fn from(text: &str) -> Self {
+        Answer::Text(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+
+    fn parse(s: &str) -> Answer {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("24000\n"), Answer::Unsigned(24000));
+        assert_eq!(parse("-3\r\n"), Answer::Integer(-3));
+        assert_eq!(parse("CMZ"), Answer::Text("CMZ".to_string()));
+        assert_eq!(
+            parse("#..#\n#..#\n"),
+            Answer::Grid(vec!["#..#".to_string(), "#..#".to_string()])
+        );
+        assert_eq!(parse("#..#\n#..#").to_string(), "#..#\n#..#");
+    }
+
+    #[test]
+    fn test_equality() {
+        assert_eq!(Answer::from(15i32), Answer::from(15usize));
+        assert_ne!(Answer::from(-1i32), Answer::from(u64::MAX));
+        assert_ne!(Answer::from(15i32), Answer::from("15"));
+        assert_ne!(Answer::from("CMZ"), Answer::Grid(vec!["CMZ".to_string()]));
+    }
+}
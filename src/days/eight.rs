@@ -1,8 +1,11 @@
 use std::{fmt::Display, str::FromStr};
 
-use super::Day;
+use anyhow::bail;
 
-pub const DAY_EIGHT: Day = Day { part_one, part_two };
+use super::{Day, Puzzle};
+use crate::{answer::Answer, parse::LineErrors, random::Rng};
+
+pub const DAY_EIGHT: Day = Day::new::<DayEight>();
 
 type Grid = Vec<Vec<u8>>;
 
@@ -15,15 +18,18 @@ struct TreeHeightMap {
 }
 
 impl TreeHeightMap {
+    /// Whether the tree is on an edge, or taller than every tree between it
+    /// and an edge. Each `from_*` grid holds the tallest tree up to and
+    /// including a position, so the tree's neighbour towards the edge is the
+    /// one to compare with.
     pub fn visible(&self, row: usize, col: usize) -> bool {
         let height = self.height[row][col];
+        let taller_than = |tallest: Option<u8>| tallest.is_none_or(|tallest| height > tallest);
 
-        let from_north = self.from_north[row][col];
-        let from_south = self.from_south[row][col];
-        let from_east = self.from_east[row][col];
-        let from_west = self.from_west[row][col];
-
-        height > from_north || height > from_south || height >= from_east || height >= from_west
+        taller_than(row.checked_sub(1).map(|row| self.from_north[row][col]))
+            || taller_than(self.from_south.get(row + 1).map(|trees| trees[col]))
+            || taller_than(col.checked_sub(1).map(|col| self.from_west[row][col]))
+            || taller_than(self.from_east[row].get(col + 1).copied())
     }
 
     pub fn width(&self) -> usize {
@@ -155,19 +161,79 @@ impl FromStr for TreeHeightMap {
     }
 }
 
-fn part_one(input: &str) -> String {
-    let m: TreeHeightMap = input.parse().unwrap();
-    let mut count = 0;
-    for row in 0..m.height() {
-        for col in 0..m.width() {
-            if m.visible(row, col) {
-                count = count + 1;
+struct DayEight;
+
+impl Puzzle for DayEight {
+    type Input = TreeHeightMap;
+
+    const PARTS: &'static [u8] = &[1];
+
+    fn parse(input: &str, _errors: &mut LineErrors) -> anyhow::Result<TreeHeightMap> {
+        input.parse()
+    }
+
+    fn part_one(m: &TreeHeightMap) -> anyhow::Result<Answer> {
+        let mut count = 0;
+        for row in 0..m.height() {
+            for col in 0..m.width() {
+                if m.visible(row, col) {
+                    count += 1;
+                }
             }
         }
+        Ok(Answer::Unsigned(count))
+    }
+
+    fn part_two(_map: &TreeHeightMap) -> anyhow::Result<Answer> {
+        bail!("not solved yet")
+    }
+
+    /// A `size` by `size` grid, at least one tree. Some grids only use low
+    /// heights, so that many trees are as tall as the trees in front of them.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let size = size.max(1);
+        let tallest = rng.range(1..=9);
+        let mut input = String::new();
+        for _ in 0..size {
+            input.extend((0..size).map(|_| char::from(b'0' + rng.range(0..=tallest) as u8)));
+            input.push('\n');
+        }
+        input
     }
-    count.to_string()
 }
 
-fn part_two(_input: &str) -> String {
-    todo!()
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+
+    #[test]
+    fn test_part_one_example() {
+        let example = include_str!("../../inputs/8/1/example.txt");
+        let answer = (DAY_EIGHT.part_one)(example);
+        assert_eq!(answer, Answer::Unsigned(21));
+    }
+
+    #[test]
+    fn test_part_one_input() {
+        let input = include_str!("../../inputs/8/1/input.txt");
+        let answer = (DAY_EIGHT.part_one)(input);
+        assert_eq!(answer, Answer::Unsigned(1538));
+    }
+
+    #[test]
+    fn test_hidden_by_equal_neighbour() {
+        // The middle tree is as tall as its east or west neighbour, and
+        // shorter than the trees on every other side.
+        for grid in ["999\n955\n999", "999\n559\n999"] {
+            let map = grid.parse::<TreeHeightMap>().unwrap();
+            assert!(!map.visible(1, 1), "{}", grid);
+            assert_eq!((DAY_EIGHT.part_one)(grid), Answer::Unsigned(8));
+        }
+
+        // An equal tree further along the row hides it too.
+        let map = "9999\n9535\n9999".parse::<TreeHeightMap>().unwrap();
+        assert!(!map.visible(1, 1));
+        assert!(!map.visible(1, 2));
+        assert!(map.visible(1, 3));
+    }
 }
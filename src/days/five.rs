@@ -3,14 +3,15 @@ use std::{collections::HashMap, str::FromStr};
 use anyhow::{bail, Context, Ok};
 use itertools::Itertools;
 
-use super::Day;
+use super::{Day, Puzzle};
+use crate::{
+    answer::Answer,
+    input::{sections, Trim},
+    parse::{LineErrors, Template},
+    random::Rng,
+};
 
-pub const DAY_FIVE: Day = Day { part_one, part_two };
-
-#[cfg(windows)]
-const LINE_ENDING: &str = "\r\n";
-#[cfg(not(windows))]
-const LINE_ENDING: &'static str = "\n";
+pub const DAY_FIVE: Day = Day::new::<DayFive>();
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum DiagramToken {
@@ -54,81 +55,66 @@ struct Instruction {
     pub count: usize,
 }
 
+const INSTRUCTION: Template = Template::new("move {} from {} to {}");
+
 impl FromStr for Instruction {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut words = s.split_whitespace();
-
-        if "move" != words.next().context("Not enough words")? {
-            bail!("Expected 'move'");
-        }
-
-        let count = words
-            .next()
-            .context("Not enough words")?
-            .parse::<usize>()
-            .context("Not a number")?;
-
-        if "from" != words.next().context("Not enough words")? {
-            bail!("Expected 'from'");
-        }
-
-        let from = words
-            .next()
-            .context("Not enough words")?
-            .parse::<usize>()
-            .context("Not a number")?;
-
-        if "to" != words.next().context("Not enough words")? {
-            bail!("Expected 'to'");
-        }
-
-        let to = words
-            .next()
-            .context("Not enough words")?
-            .parse::<usize>()
-            .context("Not a number")?;
-
+        let (count, from, to) = INSTRUCTION.parse(s)?;
         Ok(Self { from, to, count })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct CraneYard {
     crates: HashMap<usize, Vec<char>>,
 }
 
 impl CraneYard {
-    pub fn handle_instruction(&mut self, instruction: &Instruction) {
-        let to = instruction.to - 1;
-        let from = instruction.from - 1;
-        let buffer = {
-            let mut buffer = Vec::new();
-            let from_stack = self.crates.get_mut(&from).unwrap();
-            for _ in 0..instruction.count {
-                buffer.push(from_stack.pop().unwrap());
-            }
-            buffer
-        };
-        let to_stack = self.crates.get_mut(&to).unwrap();
-        to_stack.extend(buffer.into_iter());
+    /// Takes the top `count` crates off the `from` stack, bottom crate first,
+    /// after checking that both stacks exist and `from` holds enough crates.
+    fn take(&mut self, instruction: &Instruction) -> anyhow::Result<Vec<char>> {
+        if !self.crates.contains_key(&instruction.to.wrapping_sub(1)) {
+            bail!("No stack {} to move to", instruction.to);
+        }
+        let from_stack = self
+            .crates
+            .get_mut(&instruction.from.wrapping_sub(1))
+            .with_context(|| format!("No stack {} to move from", instruction.from))?;
+        let keep = from_stack
+            .len()
+            .checked_sub(instruction.count)
+            .with_context(|| {
+                format!(
+                    "Stack {} holds {} crates, cannot move {}",
+                    instruction.from,
+                    from_stack.len(),
+                    instruction.count
+                )
+            })?;
+        Ok(from_stack.split_off(keep))
     }
 
-    pub fn handle_instruction_two(&mut self, instruction: &Instruction) {
-        let to = instruction.to - 1;
-        let from = instruction.from - 1;
-        let buffer = {
-            let mut buffer = Vec::new();
-            let from_stack = self.crates.get_mut(&from).unwrap();
-            for _ in 0..instruction.count {
-                buffer.push(from_stack.pop().unwrap());
-            }
-            buffer.reverse();
-            buffer
-        };
-        let to_stack = self.crates.get_mut(&to).unwrap();
-        to_stack.extend(buffer.into_iter());
+    /// Moves crates one at a time, so they land in reverse order.
+    pub fn handle_instruction(&mut self, instruction: &Instruction) -> anyhow::Result<()> {
+        let mut buffer = self.take(instruction)?;
+        buffer.reverse();
+        self.crates
+            .entry(instruction.to - 1)
+            .or_default()
+            .extend(buffer);
+        Ok(())
+    }
+
+    /// Moves crates all at once, so they keep their order.
+    pub fn handle_instruction_two(&mut self, instruction: &Instruction) -> anyhow::Result<()> {
+        let buffer = self.take(instruction)?;
+        self.crates
+            .entry(instruction.to - 1)
+            .or_default()
+            .extend(buffer);
+        Ok(())
     }
 
     pub fn get_secret_message(&self) -> String {
@@ -164,7 +150,9 @@ impl FromStr for CraneYard {
             bail!("Invalid index row")
         }
 
-        let mut crates: HashMap<usize, Vec<char>> = HashMap::new();
+        // Every numbered stack exists, even those that start out empty.
+        let mut crates: HashMap<usize, Vec<char>> =
+            (0..idx_row.len()).map(|idx| (idx, Vec::new())).collect();
 
         for line in lines {
             for (idx, token) in line
@@ -194,73 +182,202 @@ impl FromStr for CraneYard {
     }
 }
 
-fn part_one(input: &str) -> String {
-    let blank_line = format!("{}{}", LINE_ENDING, LINE_ENDING);
-
-    let (yard, commands) = input.split_once(&blank_line).unwrap();
+struct DayFive;
 
-    let mut yard = yard.parse::<CraneYard>().unwrap();
+impl Puzzle for DayFive {
+    type Input = (CraneYard, Vec<Instruction>);
 
-    let commands = commands
-        .lines()
-        .map(|l| l.parse::<Instruction>().unwrap())
-        .collect::<Vec<_>>();
+    // The crate diagram's index row needs its trailing spaces.
+    const TRIM: Trim = Trim::End;
 
-    for command in commands.iter() {
-        yard.handle_instruction(command);
-    }
-
-    yard.get_secret_message()
-}
-
-fn part_two(input: &str) -> String {
-    let blank_line = format!("{}{}", LINE_ENDING, LINE_ENDING);
+    /// An unreadable crate yard fails the whole input, bad instructions are
+    /// recorded in `errors`. Everything after the first blank line is read as
+    /// instructions, blank lines among them are ignored. Instructions are
+    /// tried on the yard as they are read, so one that names a missing stack
+    /// or takes more crates than a stack holds is bad too.
+    fn parse(input: &str, errors: &mut LineErrors) -> anyhow::Result<Self::Input> {
+        let mut sections = sections(input).peekable();
+        let (_, yard) = sections.next().context("No crane yard")?;
+        if sections.peek().is_none() {
+            bail!("No blank line after the crane yard");
+        }
 
-    let (yard, commands) = input.split_once(&blank_line).unwrap();
+        let yard = yard.parse::<CraneYard>()?;
+
+        // Both parts move the same number of crates between the same stacks,
+        // so an instruction valid for one is valid for the other.
+        let mut trial = yard.clone();
+        let commands = sections
+            .flat_map(|(first_line, commands)| {
+                errors.parse_lines(first_line, commands, |line| {
+                    let instruction = line.parse()?;
+                    trial.handle_instruction(&instruction)?;
+                    Ok(instruction)
+                })
+            })
+            .collect();
 
-    let mut yard = yard.parse::<CraneYard>().unwrap();
+        Ok((yard, commands))
+    }
 
-    let commands = commands
-        .lines()
-        .map(|l| l.parse::<Instruction>().unwrap())
-        .collect::<Vec<_>>();
+    fn part_one((yard, commands): &Self::Input) -> anyhow::Result<Answer> {
+        let mut yard = yard.clone();
+        for command in commands.iter() {
+            yard.handle_instruction(command)?;
+        }
+        Ok(yard.get_secret_message().into())
+    }
 
-    for command in commands.iter() {
-        yard.handle_instruction_two(command);
+    fn part_two((yard, commands): &Self::Input) -> anyhow::Result<Answer> {
+        let mut yard = yard.clone();
+        for command in commands.iter() {
+            yard.handle_instruction_two(command)?;
+        }
+        Ok(yard.get_secret_message().into())
     }
 
-    yard.get_secret_message()
+    /// A yard of 2 to 9 stacks, each starting with 1 to 8 crates, and `size`
+    /// moves, at least one, that never take more crates than a stack holds.
+    /// Stacks can end up empty.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut stacks = (0..rng.range(2..=9))
+            .map(|_| {
+                (0..rng.range(1..=8))
+                    .map(|_| (b'A' + rng.below(26) as u8) as char)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let height = stacks.iter().map(Vec::len).max().unwrap_or_default();
+        let mut input = String::new();
+        for level in (0..height).rev() {
+            let row = stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(c) => format!("[{}]", c),
+                    None => "   ".to_string(),
+                })
+                .join(" ");
+            input.push_str(&row);
+            input.push('\n');
+        }
+        let index_row = (1..=stacks.len()).map(|idx| format!(" {} ", idx)).join(" ");
+        input.push_str(&index_row);
+        input.push_str("\n\n");
+
+        for _ in 0..size.max(1) {
+            let occupied = (0..stacks.len())
+                .filter(|&idx| !stacks[idx].is_empty())
+                .collect::<Vec<_>>();
+            let from = *rng.choose(&occupied);
+            let to = (from + 1 + rng.below(stacks.len() - 1)) % stacks.len();
+            let count = rng.range(1..=stacks[from].len() as u64) as usize;
+
+            let keep = stacks[from].len() - count;
+            let moved = stacks[from].split_off(keep);
+            stacks[to].extend(moved);
+            input.push_str(&format!("move {} from {} to {}\n", count, from + 1, to + 1));
+        }
+        input
+    }
 }
 
 #[cfg(test)]
 mod tests {
     pub use super::*;
+    use crate::days::RunOptions;
 
     #[test]
     fn test_part_one_example() {
         let example = include_str!("../../inputs/5/1/example.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "CMZ");
+        let answer = (DAY_FIVE.part_one)(example);
+        assert_eq!(answer, Answer::Text("CMZ".to_string()));
     }
 
     #[test]
     fn test_part_one_input() {
         let example = include_str!("../../inputs/5/1/input.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "RFFFWBPNS");
+        let answer = (DAY_FIVE.part_one)(example);
+        assert_eq!(answer, Answer::Text("RFFFWBPNS".to_string()));
     }
 
     #[test]
     fn test_part_two_example() {
         let example = include_str!("../../inputs/5/2/example.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "MCD");
+        let answer = (DAY_FIVE.part_two)(example);
+        assert_eq!(answer, Answer::Text("MCD".to_string()));
     }
 
     #[test]
     fn test_part_two_input() {
         let example = include_str!("../../inputs/5/2/input.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "CQQBBJFCS");
+        let answer = (DAY_FIVE.part_two)(example);
+        assert_eq!(answer, Answer::Text("CQQBBJFCS".to_string()));
+    }
+
+    #[test]
+    fn test_crlf_input() {
+        let example = include_str!("../../inputs/5/1/example.txt").replace('\n', "\r\n");
+        let answer = (DAY_FIVE.part_one)(&example);
+        assert_eq!(answer, Answer::Text("CMZ".to_string()));
+    }
+
+    #[test]
+    fn test_blank_line_between_moves() {
+        let example = include_str!("../../inputs/5/1/example.txt");
+        let split = example.replacen("\nmove 2 from 2 to 1", "\n\n\nmove 2 from 2 to 1", 1);
+        assert_ne!(split, example);
+        assert_eq!((DAY_FIVE.part_one)(&split), Answer::Text("CMZ".to_string()));
+
+        let mut errors = LineErrors::default();
+        let split = split.replacen("move 1 from 1 to 2", "move x from 1 to 2", 1);
+        DayFive::parse(&split, &mut errors).unwrap();
+        assert_eq!(
+            errors.iter().map(|(line, _)| *line).collect::<Vec<_>>(),
+            vec![11]
+        );
+    }
+
+    #[test]
+    fn test_impossible_moves() {
+        let example = include_str!("../../inputs/5/1/example.txt");
+        let moves = [
+            ("move 1 from 1 to 9", "No stack 9 to move to"),
+            ("move 1 from 0 to 1", "No stack 0 to move from"),
+            (
+                "move 4 from 2 to 1",
+                "Stack 2 holds 3 crates, cannot move 4",
+            ),
+        ];
+        for (bad, error) in moves {
+            // The bad move comes first, on line 6.
+            let input = example.replacen(
+                "move 1 from 2 to 1",
+                &format!("{}\nmove 1 from 2 to 1", bad),
+                1,
+            );
+
+            let strict = (DAY_FIVE.run)(&input, &[1], RunOptions::default()).unwrap_err();
+            assert_eq!(format!("{:#}", strict), format!("line 6: {}", error));
+
+            let options = RunOptions {
+                lenient: true,
+                ..RunOptions::default()
+            };
+            let run = (DAY_FIVE.run)(&input, &[1, 2], options).unwrap();
+            assert_eq!(run.skipped.len(), 1);
+            let answers = run.parts.iter().map(|(_, answer, _)| answer.clone());
+            assert_eq!(
+                answers.collect::<Vec<_>>(),
+                vec![
+                    Answer::Text("CMZ".to_string()),
+                    Answer::Text("MCD".to_string())
+                ]
+            );
+        }
+
+        // A stack that starts out empty can still be moved to.
+        let empty = "[A]    \n 1   2 \n\nmove 1 from 1 to 2\n";
+        assert_eq!((DAY_FIVE.part_one)(empty), Answer::Text(" A".to_string()));
     }
 }
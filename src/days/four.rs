@@ -1,119 +1,308 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 
-use anyhow::{anyhow, bail};
+use anyhow::{bail, Context};
 
-use super::Day;
+use super::{Day, Puzzle};
+use crate::{
+    answer::Answer,
+    interval::Interval,
+    parse::{parse_lines, split_pair, LineErrors},
+    random::Rng,
+};
 
-pub const DAY_FOUR: Day = Day { part_one, part_two };
+pub const DAY_FOUR: Day = Day::new::<DayFour>();
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-struct RangeInclusive {
-    min: i32,
-    max: i32,
+pub type RangeInclusive = Interval<u32>;
+
+/// What to do with a range written back to front, such as `9-3`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum InvertedRanges {
+    #[default]
+    Reject,
+    /// Swap the bounds, reading `9-3` as `3-9`.
+    Normalise,
 }
 
-impl PartialOrd for RangeInclusive {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
+fn parse_section(section: &str, range: &str) -> anyhow::Result<u32> {
+    let section = section.trim();
+    section.parse().with_context(|| {
+        format!(
+            "section ({}) in range ({}) is not a non-negative integer",
+            section, range
+        )
+    })
 }
 
-impl Ord for RangeInclusive {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.min.cmp(&other.min)
+fn parse_range(s: &str, inverted: InvertedRanges) -> anyhow::Result<RangeInclusive> {
+    let (start, end) = split_pair(s, "-")
+        .with_context(|| format!("range ({}) is not of the form START-END", s))?;
+    let start = parse_section(start, s)?;
+    let end = parse_section(end, s)?;
+
+    match (Interval::new(start, end), inverted) {
+        (Some(range), _) => Ok(range),
+        (None, InvertedRanges::Normalise) => Ok(Interval::new(end, start).unwrap()),
+        (None, InvertedRanges::Reject) => {
+            bail!("range ({}) is inverted, {} is after {}", s, start, end)
+        }
     }
 }
 
-impl FromStr for RangeInclusive {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AssignmentPair {
+    pub left: RangeInclusive,
+    pub right: RangeInclusive,
+}
+
+impl FromStr for AssignmentPair {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split('-');
-        let min: i32 = parts
-            .next()
-            .ok_or_else(|| anyhow!("Invalid range: {}", s))?
-            .parse()?;
-        let max: i32 = parts
-            .next()
-            .ok_or_else(|| anyhow!("Invalid range: {}", s))?
-            .parse()?;
-
-        if parts.next().is_some() {
-            bail!("Invalid range: {}", s);
-        }
-
-        Ok(RangeInclusive { min, max })
+        AssignmentPair::parse(s, InvertedRanges::default())
     }
 }
 
-impl RangeInclusive {
-    fn contains(&self, other: &RangeInclusive) -> bool {
-        self.min <= other.min && self.max >= other.max
+impl AssignmentPair {
+    pub fn parse(s: &str, inverted: InvertedRanges) -> anyhow::Result<Self> {
+        let (left, right) = split_pair(s, ",")
+            .with_context(|| format!("assignment pair ({}) is not of the form RANGE,RANGE", s))?;
+
+        let parse = |range| {
+            parse_range(range, inverted).with_context(|| format!("invalid assignment pair ({})", s))
+        };
+
+        Ok(AssignmentPair {
+            left: parse(left)?,
+            right: parse(right)?,
+        })
+    }
+
+    pub fn one_contains_other(&self) -> bool {
+        self.left.is_superset(&self.right) || self.right.is_superset(&self.left)
+    }
+
+    pub fn overlaps(&self) -> bool {
+        self.left.overlaps(&self.right)
     }
 }
 
+/// Parses one assignment pair per line, naming every bad line.
+pub fn parse_assignments(
+    input: &str,
+    inverted: InvertedRanges,
+) -> anyhow::Result<Vec<AssignmentPair>> {
+    parse_lines(input, |line| AssignmentPair::parse(line, inverted))
+}
+
+/// A run of consecutive sections assigned to the same number of elves.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-struct AssignmentPair {
-    left: RangeInclusive,
-    right: RangeInclusive,
+struct Segment {
+    start: u64,
+    /// Exclusive, so that `u32::MAX` sections can be represented.
+    end: u64,
+    elves: usize,
 }
 
-impl FromStr for AssignmentPair {
-    type Err = anyhow::Error;
+impl Segment {
+    fn len(&self) -> u64 {
+        self.end - self.start
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split(',');
-        let left: RangeInclusive = parts
-            .next()
-            .ok_or_else(|| anyhow!("Invalid assignment pair: {}", s))?
-            .parse()?;
-        let right: RangeInclusive = parts
-            .next()
-            .ok_or_else(|| anyhow!("Invalid assignment pair: {}", s))?
-            .parse()?;
-
-        if parts.next().is_some() {
-            bail!("Invalid assignment pair: {}", s);
+/// Aggregate views over every assignment pair of a crew.
+///
+/// Coverage is computed once with a sweep over the sorted range endpoints,
+/// giving the runs of sections assigned to the same number of elves. Every
+/// range endpoint is a run boundary, so questions about a range reduce to
+/// prefix counts over whole runs.
+#[derive(Debug, Clone)]
+pub struct Roster {
+    pairs: Vec<AssignmentPair>,
+    /// Contiguous from the lowest to the highest assigned section, so gaps
+    /// show up as runs with no elves.
+    segments: Vec<Segment>,
+    /// `exactly[k][i]` is how many of the first `i` segments have `k + 1` elves.
+    exactly: [Vec<usize>; 2],
+}
+
+impl Roster {
+    pub fn new(pairs: Vec<AssignmentPair>) -> Self {
+        let mut events = pairs
+            .iter()
+            .flat_map(|pair| [pair.left, pair.right])
+            .flat_map(|range| [(range.start() as u64, 1), (range.end() as u64 + 1, -1)])
+            .collect::<Vec<(u64, isize)>>();
+        events.sort_unstable();
+
+        let mut segments = Vec::new();
+        let mut elves = 0;
+        for (idx, &(position, delta)) in events.iter().enumerate() {
+            elves += delta;
+            match events.get(idx + 1) {
+                Some(&(next, _)) if next > position => segments.push(Segment {
+                    start: position,
+                    end: next,
+                    elves: elves as usize,
+                }),
+                _ => (),
+            }
         }
 
-        Ok(AssignmentPair { left, right })
+        let exactly = [1, 2].map(|k| {
+            let mut prefix = vec![0];
+            prefix.extend(segments.iter().scan(0, |count, segment| {
+                *count += (segment.elves == k) as usize;
+                Some(*count)
+            }));
+            prefix
+        });
+
+        Roster {
+            pairs,
+            segments,
+            exactly,
+        }
     }
-}
 
-impl AssignmentPair {
-    fn one_contains_other(&self) -> bool {
-        self.left.contains(&self.right) || self.right.contains(&self.left)
+    pub fn pairs(&self) -> &[AssignmentPair] {
+        &self.pairs
     }
 
-    fn overlaps(&self) -> bool {
-        let (first, second) = if self.left.min < self.right.min {
-            (&self.left, &self.right)
-        } else {
-            (&self.right, &self.left)
-        };
+    /// Sections assigned to at least one elf.
+    pub fn sections_covered(&self) -> u64 {
+        self.sections_where(|elves| elves > 0)
+    }
+
+    /// Sections assigned to more than one elf.
+    pub fn sections_shared(&self) -> u64 {
+        self.sections_where(|elves| elves > 1)
+    }
+
+    fn sections_where(&self, predicate: impl Fn(usize) -> bool) -> u64 {
+        self.segments
+            .iter()
+            .filter(|segment| predicate(segment.elves))
+            .map(Segment::len)
+            .sum()
+    }
+
+    /// `histogram[k]` is the number of sections assigned to exactly `k` elves,
+    /// counting unassigned sections between the lowest and highest assigned one.
+    pub fn coverage_histogram(&self) -> Vec<u64> {
+        let max_elves = self.segments.iter().map(|s| s.elves).max().unwrap_or(0);
+        let mut histogram = vec![0; max_elves + 1];
+        for segment in self.segments.iter() {
+            histogram[segment.elves] += segment.len();
+        }
+        histogram
+    }
+
+    /// Indices of the pairs whose sections are all also assigned to an elf
+    /// outside the pair, so the pair could be dropped without losing coverage.
+    pub fn redundant_pairs(&self) -> Vec<usize> {
+        self.pairs
+            .iter()
+            .enumerate()
+            .filter(|(_, pair)| self.is_redundant(pair))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
 
-        first.max >= second.min
+    fn is_redundant(&self, pair: &AssignmentPair) -> bool {
+        // Where only one of the pair works, the section needs a second elf;
+        // where both do, it needs a third.
+        let solo = pair
+            .left
+            .difference(&pair.right)
+            .into_iter()
+            .chain(pair.right.difference(&pair.left))
+            .map(|range| (range, 1));
+        let both = pair.left.intersection(&pair.right).map(|range| (range, 2));
+
+        solo.chain(both)
+            .all(|(range, own)| !self.has_segment_with(range, own))
+    }
+
+    /// Whether any run inside `range` has exactly `elves` elves. `range` must
+    /// start and end on run boundaries, which holds for any assigned range.
+    fn has_segment_with(&self, range: RangeInclusive, elves: usize) -> bool {
+        let first = self
+            .segments
+            .partition_point(|s| s.start < range.start() as u64);
+        let last = self
+            .segments
+            .partition_point(|s| s.start <= range.end() as u64);
+        let prefix = &self.exactly[elves - 1];
+        prefix[last] > prefix[first]
     }
 }
 
-pub fn part_one(input: &str) -> String {
-    input
-        .lines()
-        .map(|line| line.parse::<AssignmentPair>())
-        .map(|result| result.unwrap())
-        .filter(|pair| pair.one_contains_other())
-        .count()
-        .to_string()
+impl Display for Roster {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Pairs: {}", self.pairs.len())?;
+        writeln!(f, "Sections covered: {}", self.sections_covered())?;
+        writeln!(f, "Sections shared: {}", self.sections_shared())?;
+        writeln!(f, "Redundant pairs: {}", self.redundant_pairs().len())?;
+        write!(f, "Coverage (elves: sections):")?;
+        for (elves, sections) in self.coverage_histogram().iter().enumerate() {
+            if *sections > 0 {
+                write!(f, "\n  {}: {}", elves, sections)?;
+            }
+        }
+        Ok(())
+    }
 }
 
-pub fn part_two(input: &str) -> String {
-    input
-        .lines()
-        .map(|line| line.parse::<AssignmentPair>())
-        .map(|result| result.unwrap())
-        .filter(|pair| pair.overlaps())
-        .count()
-        .to_string()
+struct DayFour;
+
+impl Puzzle for DayFour {
+    type Input = Vec<AssignmentPair>;
+
+    fn parse(input: &str, errors: &mut LineErrors) -> anyhow::Result<Vec<AssignmentPair>> {
+        Ok(errors.parse_lines(1, input, |line| {
+            AssignmentPair::parse(line, InvertedRanges::Reject)
+        }))
+    }
+
+    fn part_one(pairs: &Vec<AssignmentPair>) -> anyhow::Result<Answer> {
+        Ok(pairs
+            .iter()
+            .filter(|pair| pair.one_contains_other())
+            .count()
+            .into())
+    }
+
+    fn part_two(pairs: &Vec<AssignmentPair>) -> anyhow::Result<Answer> {
+        Ok(pairs.iter().filter(|pair| pair.overlaps()).count().into())
+    }
+
+    /// `size` pairs of ranges within sections 1 to 99. The bounds of the
+    /// second range are often on or next to those of the first, where
+    /// containment and overlap are easiest to get wrong.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut input = String::new();
+        for _ in 0..size {
+            let mut left = [rng.range(1..=99), rng.range(1..=99)];
+            left.sort();
+            let near = [left[0] - 1, left[0], left[1], left[1] + 1];
+            let mut right = [(); 2].map(|_| {
+                if rng.one_in(2) {
+                    (*rng.choose(&near)).clamp(1, 99)
+                } else {
+                    rng.range(1..=99)
+                }
+            });
+            right.sort();
+            if rng.one_in(2) {
+                std::mem::swap(&mut left, &mut right);
+            }
+            input.push_str(&format!(
+                "{}-{},{}-{}\n",
+                left[0], left[1], right[0], right[1]
+            ));
+        }
+        input
+    }
 }
 
 #[cfg(test)]
@@ -123,28 +312,146 @@ mod tests {
     #[test]
     fn test_part_one_example() {
         let example = include_str!("../../inputs/4/1/example.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "2");
+        let answer = (DAY_FOUR.part_one)(example);
+        assert_eq!(answer, Answer::Unsigned(2));
     }
 
     #[test]
     fn test_part_one_input() {
         let example = include_str!("../../inputs/4/1/input.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "515");
+        let answer = (DAY_FOUR.part_one)(example);
+        assert_eq!(answer, Answer::Unsigned(515));
     }
 
     #[test]
     fn test_part_two_example() {
         let example = include_str!("../../inputs/4/2/example.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "4");
+        let answer = (DAY_FOUR.part_two)(example);
+        assert_eq!(answer, Answer::Unsigned(4));
     }
 
     #[test]
     fn test_part_two_input() {
         let example = include_str!("../../inputs/4/2/input.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "883");
+        let answer = (DAY_FOUR.part_two)(example);
+        assert_eq!(answer, Answer::Unsigned(883));
+    }
+
+    #[test]
+    fn test_inverted_ranges() {
+        assert!("9-3,1-2".parse::<AssignmentPair>().is_err());
+        let pair = AssignmentPair::parse("9-3,1-2", InvertedRanges::Normalise).unwrap();
+        assert_eq!(pair.left, Interval::new(3, 9).unwrap());
+    }
+
+    #[test]
+    fn test_malformed_ranges() {
+        let pair = " 2 - 4 ,6-8 ".parse::<AssignmentPair>().unwrap();
+        assert_eq!(pair.left, Interval::new(2, 4).unwrap());
+
+        for bad in [
+            "-3-4,5-6",
+            "3-4",
+            "3-4,5-6,7-8",
+            "3,5-6",
+            "a-4,5-6",
+            "3-4-5,6-7",
+        ] {
+            assert!(bad.parse::<AssignmentPair>().is_err(), "{}", bad);
+        }
+
+        let error = parse_assignments("2-4,6-8\n2-3,x-5", InvertedRanges::Reject).unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.starts_with("line 2: invalid assignment pair (2-3,x-5)"));
+        assert!(message.contains("section (x)"));
+    }
+
+    fn random_pairs(seed: u64, count: usize) -> impl Iterator<Item = AssignmentPair> {
+        let mut rng = Rng::new(seed);
+        let mut range = move || {
+            let (a, b) = (rng.range(0..=19) as u32, rng.range(0..=19) as u32);
+            Interval::new(a.min(b), a.max(b)).unwrap()
+        };
+        (0..count).map(move |_| AssignmentPair {
+            left: range(),
+            right: range(),
+        })
+    }
+
+    #[test]
+    fn test_contains_and_overlaps_properties() {
+        for pair in random_pairs(0x4444, 2000) {
+            let swapped = AssignmentPair {
+                left: pair.right,
+                right: pair.left,
+            };
+            assert_eq!(pair.overlaps(), swapped.overlaps(), "{:?}", pair);
+            assert_eq!(pair.one_contains_other(), swapped.one_contains_other());
+
+            let sections = |range: RangeInclusive| range.start()..=range.end();
+            let shared = sections(pair.left)
+                .filter(|s| pair.right.contains(s))
+                .count();
+            assert_eq!(pair.overlaps(), shared > 0, "{:?}", pair);
+            assert_eq!(
+                pair.one_contains_other(),
+                shared as u128 == pair.left.len().min(pair.right.len()),
+                "{:?}",
+                pair
+            );
+        }
+    }
+
+    #[test]
+    fn test_roster_example() {
+        let example = include_str!("../../inputs/4/1/example.txt");
+        let roster = Roster::new(parse_assignments(example, InvertedRanges::Reject).unwrap());
+        assert_eq!(roster.sections_covered(), 8);
+        assert_eq!(roster.sections_shared(), 7);
+        assert_eq!(roster.coverage_histogram(), vec![0, 1, 0, 0, 2, 1, 1, 2, 1]);
+        assert_eq!(roster.redundant_pairs(), vec![0, 1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_roster_matches_brute_force() {
+        for seed in 1..50 {
+            let pairs = random_pairs(seed, 1 + seed as usize % 12).collect::<Vec<_>>();
+            let roster = Roster::new(pairs.clone());
+
+            let elves_at = |section: u32, skip: Option<usize>| {
+                pairs
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| Some(*idx) != skip)
+                    .flat_map(|(_, pair)| [pair.left, pair.right])
+                    .filter(|range| range.contains(&section))
+                    .count()
+            };
+
+            let lowest = pairs.iter().map(|p| p.left.start().min(p.right.start()));
+            let highest = pairs.iter().map(|p| p.left.end().max(p.right.end()));
+            let sections = lowest.min().unwrap()..=highest.max().unwrap();
+
+            let mut histogram = vec![0; roster.coverage_histogram().len()];
+            for section in sections.clone() {
+                histogram[elves_at(section, None)] += 1;
+            }
+            assert_eq!(roster.coverage_histogram(), histogram, "seed {}", seed);
+            assert_eq!(
+                roster.sections_shared(),
+                histogram.iter().skip(2).sum::<u64>()
+            );
+
+            let redundant = (0..pairs.len())
+                .filter(|&idx| {
+                    let pair = pairs[idx];
+                    sections
+                        .clone()
+                        .filter(|s| pair.left.contains(s) || pair.right.contains(s))
+                        .all(|s| elves_at(s, Some(idx)) > 0)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(roster.redundant_pairs(), redundant, "seed {}", seed);
+        }
     }
 }
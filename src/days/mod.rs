@@ -2,16 +2,147 @@ pub mod eight;
 pub mod five;
 pub mod four;
 pub mod one;
+#[cfg(test)]
+mod reference;
 pub mod seven;
 pub mod six;
 pub mod three;
 pub mod two;
 
-pub type Solution = fn(&str) -> String;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    answer::Answer,
+    input::{normalise, Trim},
+    parse::LineErrors,
+    random::Rng,
+};
+
+/// A puzzle whose input is parsed once and shared by both parts.
+///
+/// Inputs are normalised before they are parsed, see `input::normalise`.
+pub trait Puzzle {
+    type Input;
+
+    const TRIM: Trim = Trim::Lines;
+
+    /// The parts that are solved. Running any other part is an error.
+    const PARTS: &'static [u8] = &[1, 2];
+
+    /// Invalid lines that can be skipped are recorded in `errors`, so that a
+    /// lenient run can still solve the rest. Only problems that make the whole
+    /// input unusable are returned as an error.
+    fn parse(input: &str, errors: &mut LineErrors) -> Result<Self::Input>;
+    fn part_one(input: &Self::Input) -> Result<Answer>;
+    fn part_two(input: &Self::Input) -> Result<Answer>;
+
+    /// A random valid input. What `size` counts, such as lines or elves,
+    /// depends on the day.
+    fn generate(rng: &mut Rng, size: usize) -> String;
+}
+
+pub type Solution = fn(&str) -> Answer;
+
+pub type Generator = fn(&mut Rng, usize) -> String;
+
+/// Parses the input once and solves each of the given parts with it.
+pub type Runner = fn(&str, &[u8], RunOptions) -> Result<Run>;
+
+/// How a run treats invalid lines in its input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RunOptions {
+    /// Solve with the valid lines instead of failing.
+    pub lenient: bool,
+    /// How many invalid lines to describe, the rest are only counted.
+    pub max_errors: usize,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            lenient: false,
+            max_errors: 10,
+        }
+    }
+}
+
+/// The answers of one run of a puzzle, and how long each phase took.
+#[derive(Debug)]
+pub struct Run {
+    pub parse_time: Duration,
+    /// The part number, answer and solve time of each part.
+    pub parts: Vec<(u8, Answer, Duration)>,
+    /// The lines a lenient run left out.
+    pub skipped: LineErrors,
+}
 
 pub struct Day {
     pub part_one: Solution,
     pub part_two: Solution,
+    pub run: Runner,
+    pub generate: Generator,
+    /// The parts that are solved.
+    pub parts: &'static [u8],
+}
+
+impl Day {
+    pub const fn new<P: Puzzle>() -> Self {
+        Day {
+            part_one: solve_part_one::<P>,
+            part_two: solve_part_two::<P>,
+            run: run::<P>,
+            generate: P::generate,
+            parts: P::PARTS,
+        }
+    }
+}
+
+fn parse<P: Puzzle>(input: &str, options: RunOptions) -> Result<(P::Input, LineErrors)> {
+    let mut errors = LineErrors::new(options.max_errors);
+    let parsed = P::parse(&normalise(input, P::TRIM), &mut errors)?;
+    if options.lenient {
+        Ok((parsed, errors))
+    } else {
+        Ok((errors.check(parsed)?, LineErrors::new(options.max_errors)))
+    }
+}
+
+fn solve_part_one<P: Puzzle>(input: &str) -> Answer {
+    P::part_one(&parse::<P>(input, RunOptions::default()).unwrap().0).unwrap()
+}
+
+fn solve_part_two<P: Puzzle>(input: &str) -> Answer {
+    P::part_two(&parse::<P>(input, RunOptions::default()).unwrap().0).unwrap()
+}
+
+fn run<P: Puzzle>(input: &str, parts: &[u8], options: RunOptions) -> Result<Run> {
+    if let Some(part) = parts.iter().find(|part| !P::PARTS.contains(part)) {
+        bail!("Part {} is not solved yet", part);
+    }
+
+    let start = Instant::now();
+    let (parsed, skipped) = parse::<P>(input, options)?;
+    let parse_time = start.elapsed();
+
+    let parts = parts
+        .iter()
+        .map(|&part| {
+            let start = Instant::now();
+            let answer = if part == 1 {
+                P::part_one(&parsed)
+            } else {
+                P::part_two(&parsed)
+            };
+            Ok((part, answer?, start.elapsed()))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Run {
+        parse_time,
+        parts,
+        skipped,
+    })
 }
 
 pub const DAYS: [Day; 8] = [
@@ -24,3 +155,91 @@ pub const DAYS: [Day; 8] = [
     seven::DAY_SEVEN,
     eight::DAY_EIGHT,
 ];
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+
+    #[test]
+    fn test_expected_answers() {
+        let inputs = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("inputs");
+        let mut checked = 0;
+        for (idx, day) in DAYS.iter().enumerate() {
+            for (part, solution) in [(1, day.part_one), (2, day.part_two)] {
+                for name in ["example", "input"] {
+                    let input = inputs.join(format!("{}/{}/{}.txt", idx + 1, part, name));
+                    let expected = Answer::expected_path(&input);
+                    if !expected.exists() {
+                        continue;
+                    }
+                    let text = std::fs::read_to_string(&input).unwrap();
+                    assert_eq!(
+                        solution(&text),
+                        Answer::load(&expected).unwrap(),
+                        "day {} part {} {}",
+                        idx + 1,
+                        part,
+                        name
+                    );
+                    checked += 1;
+                }
+            }
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn test_generated_inputs() {
+        for (idx, day) in DAYS.iter().enumerate() {
+            for seed in 0..10 {
+                for size in [0, 1, 2, 30] {
+                    let input = (day.generate)(&mut Rng::new(seed), size);
+                    assert_eq!(input, (day.generate)(&mut Rng::new(seed), size));
+                    let run = (day.run)(&input, day.parts, RunOptions::default());
+                    assert!(
+                        run.is_ok(),
+                        "day {} seed {} size {}: {:?}",
+                        idx + 1,
+                        seed,
+                        size,
+                        run
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let example = include_str!("../../inputs/5/1/example.txt");
+        let run = (DAYS[4].run)(example, &[1, 2], RunOptions::default()).unwrap();
+        let answers = run
+            .parts
+            .into_iter()
+            .map(|(part, answer, _)| (part, answer))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            answers,
+            vec![(1, Answer::from("CMZ")), (2, Answer::from("MCD"))]
+        );
+        assert!((DAYS[3].run)("1-2,3", &[1], RunOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_lenient_run() {
+        let input = "2-4,6-8\n2-3,x-5\n2-8,3-7\n6-6\n";
+        let error = (DAYS[3].run)(input, &[1], RunOptions::default()).unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.starts_with("line 2: "), "{}", message);
+        assert!(message.contains("\nline 4: "), "{}", message);
+
+        let options = RunOptions {
+            lenient: true,
+            max_errors: 1,
+        };
+        let run = (DAYS[3].run)(input, &[1], options).unwrap();
+        assert_eq!(run.parts[0].1, Answer::Unsigned(1));
+        assert_eq!(run.skipped.len(), 2);
+        assert_eq!(run.skipped.iter().count(), 1);
+    }
+}
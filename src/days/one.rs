@@ -1,58 +1,452 @@
-use super::Day;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt::Display,
+    io::{BufRead, Read},
+    sync::{mpsc, Mutex},
+    thread,
+};
 
-pub const DAY_ONE: Day = Day { part_one, part_two };
+use anyhow::{Context, Result};
 
-fn part_one(input: &str) -> String {
-    let mut current_elf_score = 0;
-    let mut best_elf_score = 0;
+use super::{Day, Puzzle};
+use crate::{answer::Answer, input::sections, parse::LineErrors, random::Rng};
 
-    for line in input.lines() {
-        if !line.trim().is_empty() {
-            current_elf_score += line.parse::<u64>().unwrap();
-        } else {
-            if current_elf_score > best_elf_score {
-                best_elf_score = current_elf_score;
+pub const DAY_ONE: Day = Day::new::<DayOne>();
+
+/// The total calories carried by one elf, numbered from zero in input order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ElfTotal {
+    pub index: usize,
+    pub calories: u64,
+}
+
+/// The most-laden elves offered so far, at most `k` of them.
+struct TopK {
+    k: usize,
+    // A min-heap of totals. Among equal totals the later elf sorts lower, so
+    // it is the first to be evicted.
+    heap: BinaryHeap<Reverse<(u64, Reverse<usize>)>>,
+}
+
+impl TopK {
+    fn new(k: usize) -> Self {
+        TopK {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    fn offer(&mut self, elf: ElfTotal) {
+        self.heap.push(Reverse((elf.calories, Reverse(elf.index))));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    fn into_vec(self) -> Vec<ElfTotal> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((calories, Reverse(index)))| ElfTotal { index, calories })
+            .collect()
+    }
+}
+
+/// Adds the item on a trimmed, non-blank line to the running total.
+fn add_item(total: Option<u64>, line: &str) -> Result<u64> {
+    parse_item(line)?
+        .checked_add(total.unwrap_or(0))
+        .context("calorie total overflows")
+}
+
+fn parse_item(line: &str) -> Result<u64> {
+    line.parse::<u64>()
+        .with_context(|| format!("invalid calorie count ({})", line))
+}
+
+/// The `k` elves carrying the most calories, most first.
+///
+/// Elves are separated by one or more blank lines and read one line at a
+/// time, so only `k` totals are held in memory. Ties go to the earlier elf.
+pub fn top_k<R: BufRead>(reader: R, k: usize) -> Result<Vec<ElfTotal>> {
+    let mut top = TopK::new(k);
+    let mut elves = 0;
+    let mut current = None;
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.with_context(|| format!("line {}", idx + 1))?;
+        let line = line.trim();
+        if line.is_empty() {
+            if let Some(calories) = current.take() {
+                top.offer(ElfTotal {
+                    index: elves,
+                    calories,
+                });
+                elves += 1;
+            }
+            continue;
+        }
+        current = Some(add_item(current, line).with_context(|| format!("line {}", idx + 1))?);
+    }
+    if let Some(calories) = current {
+        top.offer(ElfTotal {
+            index: elves,
+            calories,
+        });
+    }
+    Ok(top.into_vec())
+}
+
+/// How many bytes `par_top_k` hands to a thread at a time.
+pub const CHUNK_SIZE: usize = 8 << 20;
+
+/// The elves of a chunk of whole elves, numbered from the start of the chunk.
+struct Tally {
+    lines: usize,
+    elves: usize,
+    top: Vec<ElfTotal>,
+}
+
+/// Fails with the line number within the chunk, which only the caller can
+/// turn into a line number within the input.
+fn tally(chunk: &[u8], k: usize) -> std::result::Result<Tally, (usize, anyhow::Error)> {
+    let mut top = TopK::new(k);
+    let mut elves = 0;
+    let mut current = None;
+    let mut lines = 0;
+    for (idx, line) in chunk.split(|&b| b == b'\n').enumerate() {
+        lines = idx;
+        let line = std::str::from_utf8(line)
+            .context("invalid UTF-8")
+            .and_then(|line| {
+                let line = line.trim();
+                if line.is_empty() {
+                    Ok(None)
+                } else {
+                    add_item(current, line).map(Some)
+                }
+            })
+            .map_err(|err| (idx + 1, err))?;
+        match line {
+            Some(calories) => current = Some(calories),
+            None => {
+                if let Some(calories) = current.take() {
+                    top.offer(ElfTotal {
+                        index: elves,
+                        calories,
+                    });
+                    elves += 1;
+                }
+            }
+        }
+    }
+    if let Some(calories) = current {
+        top.offer(ElfTotal {
+            index: elves,
+            calories,
+        });
+        elves += 1;
+    }
+    Ok(Tally {
+        lines,
+        elves,
+        top: top.into_vec(),
+    })
+}
+
+/// The offset just past the last blank line of `buf`, if it has one.
+fn after_last_blank_line(buf: &[u8]) -> Option<usize> {
+    let mut end = buf.iter().rposition(|&b| b == b'\n')?;
+    loop {
+        let start = buf[..end]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        if buf[start..end].iter().all(u8::is_ascii_whitespace) {
+            return Some(end + 1);
+        }
+        if start == 0 {
+            return None;
+        }
+        end = start - 1;
+    }
+}
+
+/// The same as `top_k`, but the input is read in chunks of whole elves which
+/// are summed on `threads` worker threads.
+///
+/// Only a few chunks are in memory at once, however large the input.
+pub fn par_top_k<R: Read>(reader: R, k: usize, threads: usize) -> Result<Vec<ElfTotal>> {
+    par_top_k_chunked(reader, k, threads, CHUNK_SIZE)
+}
+
+fn par_top_k_chunked<R: Read>(
+    mut reader: R,
+    k: usize,
+    threads: usize,
+    chunk_size: usize,
+) -> Result<Vec<ElfTotal>> {
+    let threads = threads.max(1);
+    let (chunk_tx, chunk_rx) = mpsc::sync_channel::<(usize, Vec<u8>)>(threads);
+    let (tally_tx, tally_rx) = mpsc::channel();
+    let chunk_rx = Mutex::new(chunk_rx);
+
+    let chunks = thread::scope(|scope| -> Result<usize> {
+        for _ in 0..threads {
+            let tally_tx = tally_tx.clone();
+            let chunk_rx = &chunk_rx;
+            scope.spawn(move || loop {
+                // Take the chunk before tallying so the lock is not held.
+                let chunk = chunk_rx.lock().unwrap().recv();
+                match chunk {
+                    Ok((idx, chunk)) => tally_tx.send((idx, tally(&chunk, k))).unwrap(),
+                    Err(_) => break,
+                }
+            });
+        }
+
+        let mut chunks = 0;
+        let mut buf = Vec::with_capacity(chunk_size);
+        loop {
+            let read = (&mut reader)
+                .take(chunk_size as u64)
+                .read_to_end(&mut buf)
+                .context("Failed to read input")?;
+            let end = match (read, after_last_blank_line(&buf)) {
+                (0, _) => buf.len(),
+                (_, Some(end)) => end,
+                // An elf bigger than a chunk, keep reading until it ends.
+                (_, None) => continue,
+            };
+            let rest = buf.split_off(end);
+            if !buf.is_empty() {
+                chunk_tx.send((chunks, buf)).unwrap();
+                chunks += 1;
+            }
+            buf = rest;
+            if read == 0 {
+                break;
             }
-            current_elf_score = 0;
         }
+        drop(chunk_tx);
+        Ok(chunks)
+    })?;
+    drop(tally_tx);
+
+    let mut tallies = tally_rx.into_iter().collect::<Vec<_>>();
+    tallies.sort_unstable_by_key(|(idx, _)| *idx);
+    debug_assert_eq!(tallies.len(), chunks);
+
+    let mut top = TopK::new(k);
+    let mut lines = 0;
+    let mut elves = 0;
+    for (_, tally) in tallies {
+        let tally = tally.map_err(|(line, err)| err.context(format!("line {}", lines + line)))?;
+        for elf in tally.top {
+            top.offer(ElfTotal {
+                index: elves + elf.index,
+                ..elf
+            });
+        }
+        lines += tally.lines;
+        elves += tally.elves;
     }
+    Ok(top.into_vec())
+}
+
+/// Everything one elf is carrying, numbered from zero in input order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Elf {
+    pub index: usize,
+    pub items: Vec<u64>,
+}
 
-    if current_elf_score > best_elf_score {
-        best_elf_score = current_elf_score;
+impl Elf {
+    pub fn calories(&self) -> u64 {
+        self.items.iter().sum()
     }
-    best_elf_score.to_string()
 }
 
-fn worst_best(top_three: &mut [u64; 3]) -> (usize, &u64) {
-    top_three
-        .iter()
-        .enumerate()
-        .min_by(|(_, a), (_, b)| a.cmp(b))
-        .unwrap()
+/// Every elf of a day 1 input, in input order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Inventory {
+    pub elves: Vec<Elf>,
 }
 
-fn part_two(input: &str) -> String {
-    let mut top_three_elves = [0, 0, 0];
-    let mut current_elf_score = 0;
+impl Inventory {
+    /// Elves are separated by one or more blank lines.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut errors = LineErrors::default();
+        let inventory = Inventory::parse_with(input, &mut errors);
+        errors.check(inventory)
+    }
+
+    /// The same as `parse`, but invalid items are recorded in `errors` and
+    /// left out.
+    pub fn parse_with(input: &str, errors: &mut LineErrors) -> Self {
+        let elves = sections(input)
+            .enumerate()
+            .map(|(index, (first_line, section))| {
+                // Checking the running total here means `Elf::calories` cannot overflow.
+                let mut total = 0u64;
+                let items = errors.parse_lines(first_line, section, |line| {
+                    let item = parse_item(line.trim())?;
+                    total = total.checked_add(item).context("calorie total overflows")?;
+                    Ok(item)
+                });
+                Elf { index, items }
+            })
+            .collect();
+        Inventory { elves }
+    }
+
+    /// The calories carried by all the elves together, `None` if that
+    /// overflows even though each elf's own total fits.
+    pub fn total(&self) -> Option<u64> {
+        self.elves
+            .iter()
+            .try_fold(0u64, |total, elf| total.checked_add(elf.calories()))
+    }
+
+    /// The mean calories per elf, if there are any elves. Summed as floats, so
+    /// it is found even when `total` overflows.
+    pub fn mean(&self) -> Option<f64> {
+        let total = self
+            .elves
+            .iter()
+            .map(|elf| elf.calories() as f64)
+            .sum::<f64>();
+        (!self.elves.is_empty()).then(|| total / self.elves.len() as f64)
+    }
+
+    /// The median calories per elf, halfway between the middle two elves if
+    /// there are an even number.
+    pub fn median(&self) -> Option<f64> {
+        let mut calories = self.elves.iter().map(Elf::calories).collect::<Vec<_>>();
+        calories.sort_unstable();
+        let middle = calories.len() / 2;
+        match calories.len() {
+            0 => None,
+            len if len % 2 == 1 => Some(calories[middle] as f64),
+            _ => Some((calories[middle - 1] as f64 + calories[middle] as f64) / 2.0),
+        }
+    }
+
+    /// The elves carrying more than `threshold` calories, in input order.
+    pub fn above(&self, threshold: u64) -> Vec<&Elf> {
+        self.elves
+            .iter()
+            .filter(|elf| elf.calories() > threshold)
+            .collect()
+    }
+
+    /// The elf carrying the single most calorific item, and that item. Ties go
+    /// to the earlier elf.
+    pub fn heaviest_item(&self) -> Option<(&Elf, u64)> {
+        self.elves
+            .iter()
+            .filter_map(|elf| Some((elf, *elf.items.iter().max()?)))
+            .rev()
+            .max_by_key(|(_, item)| *item)
+    }
+
+    /// The `k` elves carrying the most calories, most first. Ties go to the
+    /// earlier elf.
+    pub fn top_k(&self, k: usize) -> Vec<ElfTotal> {
+        let mut top = TopK::new(k);
+        for elf in self.elves.iter() {
+            top.offer(ElfTotal {
+                index: elf.index,
+                calories: elf.calories(),
+            });
+        }
+        top.into_vec()
+    }
+
+    /// The calories carried by the `k` most-laden elves together, `None` if
+    /// that overflows.
+    pub fn sum_top_k(&self, k: usize) -> Option<u64> {
+        self.top_k(k)
+            .iter()
+            .try_fold(0u64, |total, elf| total.checked_add(elf.calories))
+    }
+
+    /// Where the elf with the given index places when ordered by calories,
+    /// starting from 1. Elves carrying the same calories share a rank.
+    pub fn rank(&self, index: usize) -> Option<usize> {
+        let calories = self.elves.get(index)?.calories();
+        Some(
+            1 + self
+                .elves
+                .iter()
+                .filter(|elf| elf.calories() > calories)
+                .count(),
+        )
+    }
+}
 
-    for line in input.lines() {
-        if !line.trim().is_empty() {
-            current_elf_score += line.parse::<u64>().unwrap();
-        } else {
-            let (worst_idx, worst_score) = worst_best(&mut top_three_elves);
-            if current_elf_score > *worst_score {
-                top_three_elves[worst_idx] = current_elf_score;
+impl Display for Inventory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Elves: {}", self.elves.len())?;
+        match self.total() {
+            Some(total) => writeln!(f, "Total calories: {}", total)?,
+            None => writeln!(f, "Total calories: more than {}", u64::MAX)?,
+        }
+        match (self.mean(), self.median()) {
+            (Some(mean), Some(median)) => {
+                writeln!(f, "Mean calories: {:.1}", mean)?;
+                writeln!(f, "Median calories: {:.1}", median)?;
+            }
+            _ => {
+                writeln!(f, "Mean calories: none")?;
+                writeln!(f, "Median calories: none")?;
             }
-            current_elf_score = 0;
         }
+        match self.heaviest_item() {
+            Some((elf, item)) => write!(f, "Heaviest item: {} (elf {})", item, elf.index + 1),
+            None => write!(f, "Heaviest item: none"),
+        }
+    }
+}
+
+struct DayOne;
+
+impl Puzzle for DayOne {
+    type Input = Inventory;
+
+    fn parse(input: &str, errors: &mut LineErrors) -> Result<Inventory> {
+        Ok(Inventory::parse_with(input, errors))
     }
 
-    let (worst_idx, worst_score) = worst_best(&mut top_three_elves);
-    if current_elf_score > *worst_score {
-        top_three_elves[worst_idx] = current_elf_score;
+    fn part_one(inventory: &Inventory) -> Result<Answer> {
+        let calories = inventory.sum_top_k(1).context("calorie total overflows")?;
+        Ok(calories.into())
     }
 
-    top_three_elves.iter().sum::<u64>().to_string()
+    fn part_two(inventory: &Inventory) -> Result<Answer> {
+        let calories = inventory.sum_top_k(3).context("calorie total overflows")?;
+        Ok(calories.into())
+    }
+
+    /// `size` elves carrying 1 to 15 items of 1000 to 69999 calories each.
+    /// Some elves carry the same items as the elf before them, so totals tie.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut items = Vec::new();
+        let mut input = String::new();
+        for _ in 0..size {
+            if items.is_empty() || !rng.one_in(8) {
+                items = (0..rng.range(1..=15))
+                    .map(|_| rng.range(1000..=69999))
+                    .collect();
+            }
+            for item in items.iter() {
+                input.push_str(&item.to_string());
+                input.push('\n');
+            }
+            input.push('\n');
+        }
+        input
+    }
 }
 
 #[cfg(test)]
@@ -62,28 +456,156 @@ mod tests {
     #[test]
     fn test_part_one_example() {
         let example = include_str!("../../inputs/1/1/example.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "24000");
+        let answer = (DAY_ONE.part_one)(example);
+        assert_eq!(answer, Answer::Unsigned(24000));
     }
 
     #[test]
     fn test_part_one_input() {
         let example = include_str!("../../inputs/1/1/input.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "71780");
+        let answer = (DAY_ONE.part_one)(example);
+        assert_eq!(answer, Answer::Unsigned(71780));
     }
 
     #[test]
     fn test_part_two_example() {
         let example = include_str!("../../inputs/1/2/example.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "45000");
+        let answer = (DAY_ONE.part_two)(example);
+        assert_eq!(answer, Answer::Unsigned(45000));
     }
 
     #[test]
     fn test_part_two_input() {
         let example = include_str!("../../inputs/1/2/input.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "212489");
+        let answer = (DAY_ONE.part_two)(example);
+        assert_eq!(answer, Answer::Unsigned(212489));
+    }
+
+    #[test]
+    fn test_crlf_input() {
+        let example = include_str!("../../inputs/1/1/example.txt").replace('\n', "\r\n");
+        let answer = (DAY_ONE.part_two)(&format!("\u{feff}{}\r\n\r\n", example));
+        assert_eq!(answer, Answer::Unsigned(45000));
+    }
+
+    #[test]
+    fn test_top_k() {
+        let example = include_str!("../../inputs/1/1/example.txt");
+        assert_eq!(
+            top_k(example.as_bytes(), 3).unwrap(),
+            vec![
+                ElfTotal {
+                    index: 3,
+                    calories: 24000
+                },
+                ElfTotal {
+                    index: 2,
+                    calories: 11000
+                },
+                ElfTotal {
+                    index: 4,
+                    calories: 10000
+                },
+            ]
+        );
+        assert_eq!(top_k(example.as_bytes(), 0).unwrap(), vec![]);
+        assert_eq!(top_k(example.as_bytes(), 10).unwrap().len(), 5);
+        assert_eq!(top_k("".as_bytes(), 3).unwrap(), vec![]);
+
+        let ties = top_k("5\n\n\n\n3\n2\n\n5\n".as_bytes(), 2).unwrap();
+        assert_eq!(
+            ties.iter().map(|elf| elf.index).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn test_top_k_errors() {
+        let err = top_k("1000\n\n2000\nlots\n".as_bytes(), 1).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "line 4: invalid calorie count (lots): invalid digit found in string"
+        );
+        let err = top_k(format!("{}\n1\n", u64::MAX).as_bytes(), 1).unwrap_err();
+        assert_eq!(format!("{:#}", err), "line 2: calorie total overflows");
+    }
+
+    #[test]
+    fn test_par_top_k() {
+        let example = include_str!("../../inputs/1/1/example.txt");
+        let input = include_str!("../../inputs/1/1/input.txt");
+        for text in [example, input, "", "\n\n1\n\n \n\n2\r\n3"] {
+            let expected = top_k(text.as_bytes(), 3).unwrap();
+            for chunk_size in [1, 7, 64, 4096] {
+                for threads in [1, 3] {
+                    assert_eq!(
+                        par_top_k_chunked(text.as_bytes(), 3, threads, chunk_size).unwrap(),
+                        expected
+                    );
+                }
+            }
+        }
+
+        let err = par_top_k_chunked("1\n\n2\n\n3\nx\n".as_bytes(), 1, 2, 2).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "line 6: invalid calorie count (x): invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn test_inventory() {
+        let example = include_str!("../../inputs/1/1/example.txt");
+        let inventory = Inventory::parse(example).unwrap();
+        assert_eq!(inventory.elves.len(), 5);
+        assert_eq!(
+            inventory.elves[1],
+            Elf {
+                index: 1,
+                items: vec![4000]
+            }
+        );
+        assert_eq!(inventory.total(), Some(55000));
+        assert_eq!(inventory.mean(), Some(11000.0));
+        assert_eq!(inventory.median(), Some(10000.0));
+        assert_eq!(
+            inventory
+                .above(10000)
+                .iter()
+                .map(|elf| elf.index)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        let (elf, item) = inventory.heaviest_item().unwrap();
+        assert_eq!((elf.index, item), (4, 10000));
+        assert_eq!(inventory.rank(3), Some(1));
+        assert_eq!(inventory.rank(1), Some(5));
+        assert_eq!(inventory.rank(5), None);
+
+        let ties = Inventory::parse("1\n2\n\n\n3\n\n2\n1").unwrap();
+        assert_eq!(ties.median(), Some(3.0));
+        assert_eq!(ties.rank(2), Some(1));
+        assert_eq!(ties.heaviest_item().unwrap().0.index, 1);
+
+        let empty = Inventory::parse("").unwrap();
+        assert_eq!((empty.mean(), empty.median()), (None, None));
+        assert_eq!(empty.total(), Some(0));
+
+        let huge = Inventory::parse(&format!("{}\n\n{}", u64::MAX, u64::MAX)).unwrap();
+        assert_eq!(huge.total(), None);
+        assert_eq!(huge.mean(), Some(u64::MAX as f64));
+        assert_eq!(huge.sum_top_k(1), Some(u64::MAX));
+        assert_eq!(huge.sum_top_k(2), None);
+        assert_eq!(
+            format!("{:#}", DayOne::part_two(&huge).unwrap_err()),
+            "calorie total overflows"
+        );
+        assert!(huge
+            .to_string()
+            .contains(&format!("Total calories: more than {}", u64::MAX)));
+        assert_eq!(
+            format!("{:#}", Inventory::parse("1\n\nfive").unwrap_err()),
+            "line 3: invalid calorie count (five): invalid digit found in string"
+        );
     }
 }
@@ -0,0 +1,455 @@
+//! Naive solutions to check the real ones against.
+//!
+//! Each reference reads the raw input itself and solves it the most direct
+//! way, however slow. The tests run both on generated inputs and shrink any
+//! input they disagree on to a minimal counterexample.
+
+use std::{
+    collections::{HashMap, HashSet},
+    panic,
+};
+
+use super::{Solution, DAYS};
+use crate::{answer::Answer, random::Rng};
+
+type Reference = fn(&str) -> Answer;
+
+const SEEDS: u64 = 100;
+const SIZES: [usize; 4] = [1, 3, 10, 40];
+
+fn day_one_totals(input: &str) -> Vec<u64> {
+    let mut totals = input
+        .split("\n\n")
+        .map(|elf| elf.lines().map(|line| line.parse::<u64>().unwrap()).sum())
+        .collect::<Vec<_>>();
+    totals.sort();
+    totals.reverse();
+    totals
+}
+
+fn day_one_part_one(input: &str) -> Answer {
+    day_one_totals(input)[0].into()
+}
+
+fn day_one_part_two(input: &str) -> Answer {
+    day_one_totals(input).iter().take(3).sum::<u64>().into()
+}
+
+fn day_two_score(input: &str, scores: [(&str, u64); 9]) -> Answer {
+    let scores = HashMap::from(scores);
+    input.lines().map(|line| scores[line]).sum::<u64>().into()
+}
+
+fn day_two_part_one(input: &str) -> Answer {
+    day_two_score(
+        input,
+        [
+            ("A X", 1 + 3),
+            ("A Y", 2 + 6),
+            ("A Z", 3),
+            ("B X", 1),
+            ("B Y", 2 + 3),
+            ("B Z", 3 + 6),
+            ("C X", 1 + 6),
+            ("C Y", 2),
+            ("C Z", 3 + 3),
+        ],
+    )
+}
+
+fn day_two_part_two(input: &str) -> Answer {
+    day_two_score(
+        input,
+        [
+            ("A X", 3),
+            ("A Y", 1 + 3),
+            ("A Z", 2 + 6),
+            ("B X", 1),
+            ("B Y", 2 + 3),
+            ("B Z", 3 + 6),
+            ("C X", 2),
+            ("C Y", 3 + 3),
+            ("C Z", 1 + 6),
+        ],
+    )
+}
+
+fn day_three_priority(item: char) -> u64 {
+    let letters = ('a'..='z').chain('A'..='Z').collect::<Vec<_>>();
+    letters.iter().position(|&letter| letter == item).unwrap() as u64 + 1
+}
+
+/// The total priority of the items in every one of `groups`.
+fn day_three_shared<'a>(groups: impl Iterator<Item = Vec<&'a str>>) -> Answer {
+    groups
+        .map(|group| {
+            let shared = group[0]
+                .chars()
+                .filter(|&item| group.iter().all(|items| items.contains(item)))
+                .collect::<HashSet<_>>();
+            shared.into_iter().map(day_three_priority).sum::<u64>()
+        })
+        .sum::<u64>()
+        .into()
+}
+
+fn day_three_part_one(input: &str) -> Answer {
+    day_three_shared(input.lines().map(|line| {
+        let (left, right) = line.split_at(line.len() / 2);
+        vec![left, right]
+    }))
+}
+
+fn day_three_part_two(input: &str) -> Answer {
+    let lines = input.lines().collect::<Vec<_>>();
+    day_three_shared(lines.chunks(3).map(<[&str]>::to_vec))
+}
+
+/// The sections of both elves of every pair.
+fn day_four_pairs(input: &str) -> Vec<(HashSet<u32>, HashSet<u32>)> {
+    input
+        .lines()
+        .map(|line| {
+            let bounds = line
+                .split([',', '-'])
+                .map(|bound| bound.parse::<u32>().unwrap())
+                .collect::<Vec<_>>();
+            assert!(bounds[0] <= bounds[1] && bounds[2] <= bounds[3]);
+            (
+                (bounds[0]..=bounds[1]).collect(),
+                (bounds[2]..=bounds[3]).collect(),
+            )
+        })
+        .collect()
+}
+
+fn day_four_part_one(input: &str) -> Answer {
+    day_four_pairs(input)
+        .iter()
+        .filter(|(left, right)| left.is_subset(right) || right.is_subset(left))
+        .count()
+        .into()
+}
+
+fn day_four_part_two(input: &str) -> Answer {
+    day_four_pairs(input)
+        .iter()
+        .filter(|(left, right)| !left.is_disjoint(right))
+        .count()
+        .into()
+}
+
+/// Runs the moves with `crane`, which is given the stacks and one move's
+/// count, from and to, and reads the top crate of every stack.
+fn day_five_message(input: &str, crane: fn(&mut [Vec<char>], usize, usize, usize)) -> Answer {
+    let (diagram, moves) = input.split_once("\n\n").unwrap();
+    let mut rows = diagram.lines().rev();
+    let count = rows.next().unwrap().split_whitespace().count();
+    let mut stacks = vec![Vec::new(); count];
+    for row in rows {
+        let row = row.chars().collect::<Vec<_>>();
+        for (idx, stack) in stacks.iter_mut().enumerate() {
+            match row.get(1 + 4 * idx) {
+                Some(&c) if c != ' ' => stack.push(c),
+                _ => (),
+            }
+        }
+    }
+
+    for line in moves.lines() {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let number = |idx: usize| words[idx].parse::<usize>().unwrap();
+        crane(&mut stacks, number(1), number(3) - 1, number(5) - 1);
+    }
+    stacks
+        .iter()
+        .map(|stack| stack.last().copied().unwrap_or(' '))
+        .collect::<String>()
+        .into()
+}
+
+fn day_five_part_one(input: &str) -> Answer {
+    day_five_message(input, |stacks, count, from, to| {
+        for _ in 0..count {
+            let moved = stacks[from].pop().unwrap();
+            stacks[to].push(moved);
+        }
+    })
+}
+
+fn day_five_part_two(input: &str) -> Answer {
+    day_five_message(input, |stacks, count, from, to| {
+        assert!(count <= stacks[from].len());
+        let keep = stacks[from].len() - count;
+        let moved = stacks[from].drain(keep..).collect::<Vec<_>>();
+        stacks[to].extend(moved);
+    })
+}
+
+fn day_six_marker(input: &str, window: usize) -> Answer {
+    let chars = input.trim_end().chars().collect::<Vec<_>>();
+    (window..=chars.len())
+        .find(|&end| {
+            chars[end - window..end]
+                .iter()
+                .collect::<HashSet<_>>()
+                .len()
+                == window
+        })
+        .unwrap()
+        .into()
+}
+
+fn day_six_part_one(input: &str) -> Answer {
+    day_six_marker(input, 4)
+}
+
+fn day_six_part_two(input: &str) -> Answer {
+    day_six_marker(input, 14)
+}
+
+/// The size of every directory, with paths kept as lists of names so that
+/// no joining or splitting of path strings is involved.
+fn day_seven_sizes(input: &str) -> Vec<usize> {
+    let mut cwd: Vec<&str> = Vec::new();
+    let mut dirs = HashSet::from([Vec::new()]);
+    let mut files = HashMap::new();
+    for line in input.lines() {
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["$", "cd", "/"] => cwd.clear(),
+            ["$", "cd", path] => {
+                for name in path.split('/') {
+                    match name {
+                        ".." => {
+                            cwd.pop();
+                        }
+                        name => cwd.push(name),
+                    }
+                }
+            }
+            ["$", "ls"] => (),
+            ["dir", name] => {
+                dirs.insert([cwd.as_slice(), &[*name]].concat());
+            }
+            [size, name] => {
+                let size = size.parse::<usize>().unwrap();
+                files.insert([cwd.as_slice(), &[*name]].concat(), size);
+            }
+            _ => panic!("unexpected line {}", line),
+        }
+    }
+
+    dirs.iter()
+        .map(|dir| {
+            files
+                .iter()
+                .filter(|(path, _)| path.starts_with(dir))
+                .map(|(_, size)| size)
+                .sum()
+        })
+        .collect()
+}
+
+fn day_seven_part_one(input: &str) -> Answer {
+    day_seven_sizes(input)
+        .into_iter()
+        .filter(|&size| size <= 100000)
+        .sum::<usize>()
+        .into()
+}
+
+fn day_seven_part_two(input: &str) -> Answer {
+    let sizes = day_seven_sizes(input);
+    let used = sizes.iter().max().unwrap();
+    let needed = used.saturating_sub(40000000);
+    sizes
+        .iter()
+        .filter(|&&size| size >= needed)
+        .min()
+        .copied()
+        .unwrap()
+        .into()
+}
+
+fn day_eight_part_one(input: &str) -> Answer {
+    let grid = input
+        .lines()
+        .map(|line| line.bytes().map(|b| b - b'0').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut visible = 0;
+    for row in 0..grid.len() {
+        for col in 0..grid[row].len() {
+            let height = grid[row][col];
+            let lines_of_sight: [Vec<u8>; 4] = [
+                grid[row][..col].to_vec(),
+                grid[row][col + 1..].to_vec(),
+                grid[..row].iter().map(|trees| trees[col]).collect(),
+                grid[row + 1..].iter().map(|trees| trees[col]).collect(),
+            ];
+            if lines_of_sight
+                .iter()
+                .any(|trees| trees.iter().all(|&tree| tree < height))
+            {
+                visible += 1;
+            }
+        }
+    }
+    Answer::Unsigned(visible)
+}
+
+/// Both answers to `input` if they differ. Answers that panic, as on the
+/// invalid inputs tried while shrinking, do not count as a difference.
+fn mismatch(solution: Solution, reference: Reference, input: &str) -> Option<(Answer, Answer)> {
+    let (answer, expected) = panic::catch_unwind(|| (solution(input), reference(input))).ok()?;
+    (answer != expected).then_some((answer, expected))
+}
+
+/// Tries removing runs of items, halving the run length down to one, and
+/// keeps every removal after which `fails` still holds.
+fn remove_runs<T: Clone>(items: &mut Vec<T>, fails: impl Fn(&[T]) -> bool) -> bool {
+    let mut shrunk = false;
+    let mut len = (items.len() / 2).max(1);
+    while len > 0 {
+        let mut start = 0;
+        while start < items.len() {
+            let mut candidate = items.clone();
+            candidate.drain(start..(start + len).min(items.len()));
+            if fails(&candidate) {
+                *items = candidate;
+                shrunk = true;
+            } else {
+                start += len;
+            }
+        }
+        len /= 2;
+    }
+    shrunk
+}
+
+fn render(rows: &[Vec<char>]) -> String {
+    rows.iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect()
+}
+
+fn transpose(rows: &[Vec<char>]) -> Vec<Vec<char>> {
+    let width = rows.first().map_or(0, Vec::len);
+    (0..width)
+        .map(|col| rows.iter().map(|row| row[col]).collect())
+        .collect()
+}
+
+/// Removes lines from `input`, then characters if only one line is left, or
+/// columns if the lines form a grid, for as long as `fails` still holds.
+fn shrink(input: &str, fails: impl Fn(&str) -> bool) -> String {
+    let mut rows = input
+        .lines()
+        .map(|line| line.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    loop {
+        let mut shrunk = remove_runs(&mut rows, |rows| fails(&render(rows)));
+        if let [row] = rows.as_mut_slice() {
+            shrunk |= remove_runs(row, |row| fails(&render(&[row.to_vec()])));
+        } else if rows.iter().all(|row| row.len() == rows[0].len()) {
+            let mut columns = transpose(&rows);
+            shrunk |= remove_runs(&mut columns, |columns| fails(&render(&transpose(columns))));
+            if shrunk {
+                rows = transpose(&columns);
+            }
+        }
+        if !shrunk {
+            return render(&rows);
+        }
+    }
+}
+
+/// Compares a part of a day with its reference on generated inputs, and fails
+/// with the smallest input found that they disagree on. Parts the day does not
+/// solve yet are skipped.
+fn check(day: usize, part: u8, reference: Reference) {
+    if !DAYS[day - 1].parts.contains(&part) {
+        return;
+    }
+    let solution = match part {
+        1 => DAYS[day - 1].part_one,
+        _ => DAYS[day - 1].part_two,
+    };
+    for seed in 0..SEEDS {
+        for size in SIZES {
+            let input = (DAYS[day - 1].generate)(&mut Rng::new(seed), size);
+            if solution(&input) == reference(&input) {
+                continue;
+            }
+            let input = shrink(&input, |input| {
+                mismatch(solution, reference, input).is_some()
+            });
+            let (answer, expected) = mismatch(solution, reference, &input).unwrap();
+            panic!(
+                "day {} part {} answered {} instead of {} (seed {}, size {}) for:\n{}",
+                day, part, answer, expected, seed, size, input
+            );
+        }
+    }
+}
+
+#[test]
+fn test_shrink() {
+    let input = "1\n2\n3\n4\n5\n6\n7\n";
+    assert_eq!(
+        shrink(input, |input| input.contains('3') && input.contains('6')),
+        "3\n6\n"
+    );
+    assert_eq!(shrink("abcdef\n", |input| input.contains('d')), "d\n");
+    assert_eq!(
+        shrink("123\n456\n789\n", |input| input.contains('5')
+            && input.contains('8')),
+        "5\n8\n"
+    );
+}
+
+#[test]
+fn test_day_one() {
+    check(1, 1, day_one_part_one);
+    check(1, 2, day_one_part_two);
+}
+
+#[test]
+fn test_day_two() {
+    check(2, 1, day_two_part_one);
+    check(2, 2, day_two_part_two);
+}
+
+#[test]
+fn test_day_three() {
+    check(3, 1, day_three_part_one);
+    check(3, 2, day_three_part_two);
+}
+
+#[test]
+fn test_day_four() {
+    check(4, 1, day_four_part_one);
+    check(4, 2, day_four_part_two);
+}
+
+#[test]
+fn test_day_five() {
+    check(5, 1, day_five_part_one);
+    check(5, 2, day_five_part_two);
+}
+
+#[test]
+fn test_day_six() {
+    check(6, 1, day_six_part_one);
+    check(6, 2, day_six_part_two);
+}
+
+#[test]
+fn test_day_seven() {
+    check(7, 1, day_seven_part_one);
+    check(7, 2, day_seven_part_two);
+}
+
+#[test]
+fn test_day_eight() {
+    check(8, 1, day_eight_part_one);
+}
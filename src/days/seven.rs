@@ -1,19 +1,45 @@
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
+    fs,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
 use anyhow::{anyhow, bail, Context};
+use itertools::Itertools;
 
-use super::Day;
+use super::{Day, Puzzle};
+use crate::{
+    answer::Answer,
+    parse::{parse_lines, words, LineErrors},
+    random::Rng,
+};
+
+pub const DAY_SEVEN: Day = Day::new::<DaySeven>();
 
-pub const DAY_SEVEN: Day = Day { part_one, part_two };
+/// How symlinks are treated when reading a directory from disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symlinks {
+    Follow,
+    Skip,
+}
+
+/// Walks a directory on disk and returns a `$ cd` / `$ ls` transcript of it
+/// that the day 7 solutions can ingest.
+pub fn transcript_from_dir(path: &Path, symlinks: Symlinks) -> anyhow::Result<String> {
+    Ok(FileSystem::from_dir(path, symlinks)?.transcript())
+}
 
 #[derive(Debug)]
 enum Token {
     CD(String),
     LS,
+    Pwd,
+    Rm(String),
+    Mkdir(String),
+    Touch(File),
+    Total(usize),
     Dir(String),
     File(File),
 }
@@ -23,38 +49,80 @@ impl Display for Token {
         match self {
             Token::CD(dir) => write!(f, "cd ({})", dir),
             Token::LS => write!(f, "ls"),
+            Token::Pwd => write!(f, "pwd"),
+            Token::Rm(path) => write!(f, "rm ({})", path),
+            Token::Mkdir(path) => write!(f, "mkdir ({})", path),
+            Token::Touch(File { size, name }) => {
+                write!(f, "touch, size ({}) name ({})", size, name)
+            }
+            Token::Total(blocks) => write!(f, "total ({})", blocks),
             Token::Dir(dir) => write!(f, "dir ({})", dir),
             Token::File(File { size, name }) => write!(f, "file, size ({}) name ({})", size, name),
         }
     }
 }
 
-impl FromStr for Token {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut words = s.split_whitespace();
+/// Whether `word` looks like the mode column of an `ls -l` line, e.g. `drwxr-xr-x`.
+fn is_ls_mode(word: &str) -> bool {
+    let mut chars = word.chars();
+    matches!(chars.next(), Some('-' | 'd' | 'l' | 'b' | 'c' | 'p' | 's'))
+        && word.len() >= 10
+        && chars.all(|c| "rwxsStT-.+@".contains(c))
+}
 
-        let first_word = words.next();
-        let second_word = words.next();
-        let third_word = words.next();
+fn is_flag(word: &&str) -> bool {
+    word.starts_with('-')
+}
 
-        if words.next().is_some() {
-            bail!("({}) has too many parts to be a Token", s);
-        }
+fn parse_size(size: &str) -> anyhow::Result<usize> {
+    size.parse()
+        .with_context(|| format!("{} is not a valid size", size))
+}
 
-        match (first_word, second_word, third_word) {
-            (Some("$"), Some("cd"), Some(dir)) => Ok(Token::CD(dir.to_string())),
-            (Some("$"), Some("ls"), None) => Ok(Token::LS),
-            (Some("dir"), Some(dir), None) => Ok(Token::Dir(dir.to_string())),
-            (Some(size), Some(name), None) => Ok(Token::File(File {
-                size: size
-                    .parse()
-                    .with_context(|| format!("{} is not a valid size", size))?,
+impl FromStr for Token {
+    type Err = anyhow::Error;
 
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = words(s)?;
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+
+        match words.as_slice() {
+            ["$", "cd", path] => Ok(Token::CD(path.to_string())),
+            ["$", "ls", flags @ ..] if flags.iter().all(is_flag) => Ok(Token::LS),
+            ["$", "pwd"] => Ok(Token::Pwd),
+            ["$", "rm", flags @ .., path] if flags.iter().all(is_flag) => {
+                Ok(Token::Rm(path.to_string()))
+            }
+            ["$", "mkdir", flags @ .., path] if flags.iter().all(is_flag) => {
+                Ok(Token::Mkdir(path.to_string()))
+            }
+            ["$", "touch", size, path] => Ok(Token::Touch(File {
+                size: parse_size(size)?,
+                name: path.to_string(),
+            })),
+            ["$", ..] => Err(anyhow!("{} is not a supported command", s)),
+            ["total", blocks] => Ok(Token::Total(parse_size(blocks)?)),
+            ["dir", dir] => Ok(Token::Dir(dir.to_string())),
+            [mode, _links, _owner, _group, size, _month, _day, _time, name @ ..]
+                if is_ls_mode(mode) && !name.is_empty() =>
+            {
+                // Symlinks are listed as `name -> target`; only the link itself is kept.
+                let name = name.join(" ");
+                let name = name.split(" -> ").next().unwrap_or_default().to_string();
+                if mode.starts_with('d') {
+                    Ok(Token::Dir(name))
+                } else {
+                    Ok(Token::File(File {
+                        size: parse_size(size)?,
+                        name,
+                    }))
+                }
+            }
+            [size, name] => Ok(Token::File(File {
+                size: parse_size(size)?,
                 name: name.to_string(),
             })),
-            (_, _, _) => Err(anyhow!("{} is not a valid token", s)),
+            _ => Err(anyhow!("{} is not a valid token", s)),
         }
     }
 }
@@ -79,28 +147,46 @@ struct FileSystem {
 impl FileSystem {
     pub fn new() -> Self {
         FileSystem {
-            dirs: HashMap::new(),
+            dirs: HashMap::from([("/".to_string(), Directory::default())]),
             current_path: vec!["/".to_string()],
         }
     }
 
-    pub fn cd(&mut self, dir: &str) {
-        if dir == "/" {
-            self.current_path = vec!["/".to_string()];
-        } else if dir == ".." {
-            self.current_path.pop();
+    /// Resolves a possibly multi-segment path such as `a/b/../c` against the
+    /// current directory. `..` at the root stays at the root.
+    fn resolve(&self, path: &str) -> Vec<String> {
+        let mut resolved = if path.starts_with('/') {
+            vec!["/".to_string()]
         } else {
-            self.current_path.push(dir.to_string());
+            self.current_path.clone()
+        };
+
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => (),
+                ".." => {
+                    if resolved.len() > 1 {
+                        resolved.pop();
+                    }
+                }
+                name => resolved.push(name.to_string()),
+            }
         }
+        resolved
     }
 
-    pub fn make_dir_if_needed(&mut self, dir: &str) {
-        let parent_path = self.current_path();
-        let parent_dir = self.dirs.entry(parent_path).or_default();
-        parent_dir.child_dirs.insert(dir.to_string());
-        if !self.dirs.contains_key(dir) {
-            self.dirs.insert(dir.to_string(), Default::default());
+    pub fn cd(&mut self, path: &str) {
+        self.current_path = self.resolve(path);
+    }
+
+    /// Creates the directory at `path` along with any missing parents.
+    pub fn make_dir_if_needed(&mut self, path: &str) {
+        let resolved = self.resolve(path);
+        for depth in 1..resolved.len() {
+            let parent = self.dirs.entry(resolved[..depth].join("/")).or_default();
+            parent.child_dirs.insert(resolved[depth].clone());
         }
+        self.dirs.entry(resolved.join("/")).or_default();
     }
 
     pub fn current_path(&self) -> String {
@@ -110,16 +196,63 @@ impl FileSystem {
     pub fn add_file(&mut self, file: File) {
         let path = self.current_path();
         let dir = self.dirs.entry(path).or_default();
-        dir.files.push(file);
+        match dir.files.iter_mut().find(|f| f.name == file.name) {
+            Some(existing) => existing.size = file.size,
+            None => dir.files.push(file),
+        }
+    }
+
+    /// Creates (or resizes) the file at `path`, creating missing parent directories.
+    pub fn touch(&mut self, path: &str, size: usize) {
+        let (parent, name) = path.rsplit_once('/').unwrap_or((".", path));
+        let parent = if parent.is_empty() { "/" } else { parent };
+
+        let parent = self.resolve(parent);
+        let saved_path = std::mem::replace(&mut self.current_path, parent);
+        self.make_dir_if_needed(".");
+        self.add_file(File {
+            size,
+            name: name.to_string(),
+        });
+        self.current_path = saved_path;
+    }
+
+    /// Removes the file or directory tree at `path`.
+    pub fn rm(&mut self, path: &str) -> anyhow::Result<()> {
+        let mut resolved = self.resolve(path);
+        if resolved.len() == 1 {
+            bail!("refusing to remove /");
+        }
+        let name = resolved.pop().unwrap_or_default();
+        let parent = self
+            .dirs
+            .get_mut(&resolved.join("/"))
+            .with_context(|| format!("cannot remove {}: no such file or directory", path))?;
+
+        if parent.child_dirs.remove(&name) {
+            resolved.push(name);
+            let removed = resolved.join("/");
+            let removed_prefix = format!("{}/", removed);
+            self.dirs
+                .retain(|key, _| key != &removed && !key.starts_with(&removed_prefix));
+        } else if let Some(idx) = parent.files.iter().position(|f| f.name == name) {
+            parent.files.remove(idx);
+        } else {
+            bail!("cannot remove {}: no such file or directory", path);
+        }
+        Ok(())
     }
 
-    pub fn process_token(&mut self, token: Token) {
+    pub fn process_token(&mut self, token: Token) -> anyhow::Result<()> {
         match token {
             Token::CD(dir) => self.cd(&dir),
-            Token::LS => (),
-            Token::Dir(dir) => self.make_dir_if_needed(&dir),
+            Token::LS | Token::Pwd | Token::Total(_) => (),
+            Token::Rm(path) => self.rm(&path)?,
+            Token::Mkdir(path) | Token::Dir(path) => self.make_dir_if_needed(&path),
+            Token::Touch(File { size, name }) => self.touch(&name, size),
             Token::File(file) => self.add_file(file),
         }
+        Ok(())
     }
 
     pub fn total_size(&self, dir_path: &str) -> usize {
@@ -131,62 +264,278 @@ impl FileSystem {
                 .map(|child| self.total_size(&format!("{}/{}", dir_path, child)))
                 .sum::<usize>()
     }
-}
 
-impl FromStr for FileSystem {
-    type Err = anyhow::Error;
+    pub fn from_dir(path: &Path, symlinks: Symlinks) -> anyhow::Result<Self> {
+        let mut fs = FileSystem::new();
+        let root = fs::canonicalize(path)
+            .with_context(|| format!("cannot read directory {}", path.display()))?;
+        fs.walk(&root, symlinks, &mut vec![root.clone()])?;
+        Ok(fs)
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    /// Adds the contents of `dir` to the current directory. `ancestors` holds
+    /// the canonical paths being walked so followed symlinks cannot loop.
+    fn walk(
+        &mut self,
+        dir: &Path,
+        symlinks: Symlinks,
+        ancestors: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let mut entries = fs::read_dir(dir)
+            .with_context(|| format!("cannot read directory {}", dir.display()))?
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|name| anyhow!("{:?} is not a valid UTF-8 name", name))?;
+
+            let metadata = if entry.file_type()?.is_symlink() {
+                match (symlinks, fs::metadata(entry.path())) {
+                    (Symlinks::Follow, Ok(metadata)) => metadata,
+                    // Skipped or dangling links.
+                    _ => continue,
+                }
+            } else {
+                entry.metadata()?
+            };
+
+            if metadata.is_dir() {
+                let canonical = fs::canonicalize(entry.path())?;
+                if ancestors.contains(&canonical) {
+                    continue;
+                }
+                self.make_dir_if_needed(&name);
+                self.cd(&name);
+                ancestors.push(canonical.clone());
+                self.walk(&canonical, symlinks, ancestors)?;
+                ancestors.pop();
+                self.cd("..");
+            } else {
+                self.add_file(File {
+                    size: metadata.len() as usize,
+                    name,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits a `$ cd` / `$ ls` session that rebuilds this file system when parsed.
+    pub fn transcript(&self) -> String {
+        let mut transcript = "$ cd /\n".to_string();
+        self.write_transcript("/", &mut transcript);
+        transcript
+    }
+
+    fn write_transcript(&self, dir_path: &str, transcript: &mut String) {
+        let dir = self.dirs.get(dir_path).unwrap();
+        let child_dirs = dir.child_dirs.iter().sorted().collect::<Vec<_>>();
+
+        transcript.push_str("$ ls\n");
+        for child in child_dirs.iter() {
+            transcript.push_str(&format!("dir {}\n", quote(child)));
+        }
+        for file in dir.files.iter().sorted_by(|a, b| a.name.cmp(&b.name)) {
+            transcript.push_str(&format!("{} {}\n", file.size, quote(&file.name)));
+        }
+        for child in child_dirs {
+            transcript.push_str(&format!("$ cd {}\n", quote(child)));
+            self.write_transcript(&format!("{}/{}", dir_path, child), transcript);
+            transcript.push_str("$ cd ..\n");
+        }
+    }
+
+    /// A random tree of `files` files in a fifth as many directories. The
+    /// sizes add up to between 40000001 and 60000000, so that both parts have
+    /// an answer.
+    pub fn generate(rng: &mut Rng, files: usize) -> Self {
         let mut fs = FileSystem::new();
-        for line in s.lines() {
-            let token = line.parse::<Token>()?;
-            fs.process_token(token);
+        let mut dirs = vec!["/".to_string()];
+        for _ in 0..files / 5 {
+            let parent = rng.choose(&dirs).clone();
+            fs.cd(&parent);
+            let name = fs.unused_name(rng, false);
+            fs.make_dir_if_needed(&name);
+            dirs.push(format!("{}/{}", parent.trim_end_matches('/'), name));
         }
-        Ok(fs)
+
+        let max_size = (60_000_000 / files.max(1)).clamp(1, 300_000) as u64;
+        let mut sizes = (0..files)
+            .map(|_| rng.range(1..=max_size) as usize)
+            .collect::<Vec<_>>();
+        let total = sizes.iter().sum::<usize>();
+        let target = rng.range(40_000_001..=60_000_000) as usize;
+        if total < target {
+            sizes.push(target - total);
+        }
+        for size in sizes {
+            fs.cd(&rng.choose(&dirs).clone());
+            let name = fs.unused_name(rng, true);
+            fs.add_file(File { size, name });
+        }
+
+        fs.cd("/");
+        fs
     }
-}
 
-fn part_one(input: &str) -> String {
-    let fs = input.parse::<FileSystem>().unwrap();
+    /// A name of 1 to 8 letters that nothing in the current directory has.
+    fn unused_name(&self, rng: &mut Rng, extension: bool) -> String {
+        let dir = self.dirs.get(&self.current_path()).unwrap();
+        loop {
+            let mut name = (0..rng.range(1..=8))
+                .map(|_| (b'a' + rng.below(26) as u8) as char)
+                .collect::<String>();
+            if extension && rng.one_in(2) {
+                let suffix = rng.choose(&["txt", "dat", "log", "bin"]);
+                name.push('.');
+                name.push_str(suffix);
+            }
+            if !dir.child_dirs.contains(&name) && dir.files.iter().all(|f| f.name != name) {
+                return name;
+            }
+        }
+    }
 
-    let mut to_visit = vec!["/".to_string()];
-    let mut answer = 0;
-    while !to_visit.is_empty() {
-        let dir_path = to_visit.pop().unwrap();
-        let dir = fs.dirs.get(&dir_path).unwrap();
-        for child in dir.child_dirs.iter() {
-            to_visit.push(format!("{}/{}", dir_path, child));
+    /// Like `transcript`, but visits directories and lists entries in a random
+    /// order, sometimes lists a directory twice, and sometimes moves by way of
+    /// `$ cd /` and a path of several directories instead of one at a time.
+    pub fn random_transcript(&self, rng: &mut Rng) -> String {
+        let mut transcript = "$ cd /\n".to_string();
+        self.write_random_transcript(rng, &mut vec!["/".to_string()], &mut transcript);
+        transcript
+    }
+
+    fn write_random_transcript(
+        &self,
+        rng: &mut Rng,
+        path: &mut Vec<String>,
+        transcript: &mut String,
+    ) {
+        let dir = self.dirs.get(&path.join("/")).unwrap();
+        let mut child_dirs = dir.child_dirs.iter().sorted().collect::<Vec<_>>();
+
+        for _ in 0..if rng.one_in(10) { 2 } else { 1 } {
+            let mut entries = child_dirs
+                .iter()
+                .map(|child| format!("dir {}\n", quote(child)))
+                .chain(
+                    dir.files
+                        .iter()
+                        .map(|file| format!("{} {}\n", file.size, quote(&file.name))),
+                )
+                .collect::<Vec<_>>();
+            rng.shuffle(&mut entries);
+            transcript.push_str("$ ls\n");
+            transcript.extend(entries);
         }
-        let dir_size = fs.total_size(&dir_path);
-        if dir_size <= 100000 {
-            answer += dir_size;
+
+        // `$ cd` along a path from the root, which is `/` itself.
+        let cd_from_root = |path: &[String], transcript: &mut String| {
+            transcript.push_str("$ cd /\n");
+            if path.len() > 1 {
+                let path = path[1..].iter().map(|name| quote(name)).join("/");
+                transcript.push_str(&format!("$ cd {}\n", path));
+            }
+        };
+        rng.shuffle(&mut child_dirs);
+        for child in child_dirs {
+            path.push(child.clone());
+            if rng.one_in(8) {
+                cd_from_root(path, transcript);
+            } else {
+                transcript.push_str(&format!("$ cd {}\n", quote(child)));
+            }
+            self.write_random_transcript(rng, path, transcript);
+            path.pop();
+            if rng.one_in(8) {
+                cd_from_root(path, transcript);
+            } else {
+                transcript.push_str("$ cd ..\n");
+            }
         }
     }
-    answer.to_string()
 }
 
-fn part_two(input: &str) -> String {
-    let fs = input.parse::<FileSystem>().unwrap();
+/// Quotes `name` so that `parse::words` reads it back as a single word.
+fn quote(name: &str) -> String {
+    if !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || "'\"\\".contains(c)) {
+        return name.to_string();
+    }
+    let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escaped)
+}
+
+impl FromStr for FileSystem {
+    type Err = anyhow::Error;
 
-    let mut to_visit = vec!["/".to_string()];
-    let mut candidates = Vec::new();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fs = FileSystem::new();
+        parse_lines(s, |line| fs.process_token(line.parse()?))?;
+        Ok(fs)
+    }
+}
 
-    let used_space = fs.total_size("/");
-    let free_space = 70000000 - used_space;
-    let need_to_free = 30000000 - free_space;
+struct DaySeven;
 
-    while !to_visit.is_empty() {
-        let dir_path = to_visit.pop().unwrap();
-        let dir = fs.dirs.get(&dir_path).unwrap();
-        for child in dir.child_dirs.iter() {
-            to_visit.push(format!("{}/{}", dir_path, child));
+impl Puzzle for DaySeven {
+    type Input = FileSystem;
+
+    fn parse(input: &str, errors: &mut LineErrors) -> anyhow::Result<FileSystem> {
+        let mut fs = FileSystem::new();
+        errors.parse_lines(1, input, |line| fs.process_token(line.parse()?));
+        Ok(fs)
+    }
+
+    fn part_one(fs: &FileSystem) -> anyhow::Result<Answer> {
+        let mut to_visit = vec!["/".to_string()];
+        let mut answer = 0;
+        while let Some(dir_path) = to_visit.pop() {
+            let dir = fs.dirs.get(&dir_path).unwrap();
+            for child in dir.child_dirs.iter() {
+                to_visit.push(format!("{}/{}", dir_path, child));
+            }
+            let dir_size = fs.total_size(&dir_path);
+            if dir_size <= 100000 {
+                answer += dir_size;
+            }
         }
-        let dir_size = fs.total_size(&dir_path);
-        if dir_size >= need_to_free {
-            candidates.push(dir_size);
+        Ok(answer.into())
+    }
+
+    fn part_two(fs: &FileSystem) -> anyhow::Result<Answer> {
+        let mut to_visit = vec!["/".to_string()];
+        let mut candidates = Vec::new();
+
+        let used_space = fs.total_size("/");
+        let free_space = 70000000_usize
+            .checked_sub(used_space)
+            .context("files take up more than the whole disk")?;
+        let need_to_free = 30000000_usize.saturating_sub(free_space);
+
+        while let Some(dir_path) = to_visit.pop() {
+            let dir = fs.dirs.get(&dir_path).unwrap();
+            for child in dir.child_dirs.iter() {
+                to_visit.push(format!("{}/{}", dir_path, child));
+            }
+            let dir_size = fs.total_size(&dir_path);
+            if dir_size >= need_to_free {
+                candidates.push(dir_size);
+            }
         }
+        let smallest = candidates
+            .into_iter()
+            .min()
+            .context("no directory frees enough space")?;
+        Ok(smallest.into())
+    }
+
+    /// A randomly ordered transcript of `size` files, see `FileSystem::generate`.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        FileSystem::generate(rng, size).random_transcript(rng)
     }
-    candidates.into_iter().min().unwrap().to_string()
 }
 
 #[cfg(test)]
@@ -196,28 +545,145 @@ mod tests {
     #[test]
     fn test_part_one_example() {
         let example = include_str!("../../inputs/7/1/example.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "95437");
+        let answer = (DAY_SEVEN.part_one)(example);
+        assert_eq!(answer, Answer::Unsigned(95437));
     }
 
     #[test]
     fn test_part_one_input() {
         let example = include_str!("../../inputs/7/1/input.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "1443806");
+        let answer = (DAY_SEVEN.part_one)(example);
+        assert_eq!(answer, Answer::Unsigned(1443806));
     }
 
     #[test]
     fn test_part_two_example() {
         let example = include_str!("../../inputs/7/2/example.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "24933642");
+        let answer = (DAY_SEVEN.part_two)(example);
+        assert_eq!(answer, Answer::Unsigned(24933642));
     }
 
     #[test]
     fn test_part_two_input() {
         let example = include_str!("../../inputs/7/2/input.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "942298");
+        let answer = (DAY_SEVEN.part_two)(example);
+        assert_eq!(answer, Answer::Unsigned(942298));
+    }
+
+    #[test]
+    fn test_part_two_small_tree() {
+        // Enough space is already free, so the smallest directory will do.
+        let answer = (DAY_SEVEN.part_two)("$ cd /\n$ ls\n100 a");
+        assert_eq!(answer, Answer::Unsigned(100));
+
+        let full = "$ cd /\n$ ls\n80000000 a".parse::<FileSystem>().unwrap();
+        assert!(DaySeven::part_two(&full).is_err());
+    }
+
+    #[test]
+    fn test_extended_transcript() {
+        let transcript = "\
+$ cd /
+$ mkdir -p a/b
+$ touch 100 a/b/one.txt
+$ cd a/b/../b
+$ pwd
+$ touch 20 'two words.txt'
+$ ls -l
+total 8
+-rw-r--r-- 1 elf elves 300 Dec  7 09:00 three words here.txt
+drwxr-xr-x 2 elf elves 4096 Dec  7 09:00 c
+lrwxrwxrwx 1 elf elves 5 Dec  7 09:00 link -> one.txt
+$ cd c
+$ ls
+4000 \"quoted name\"
+$ cd /
+$ rm -r a/b/c
+$ rm a/b/one.txt
+";
+        let fs = transcript.parse::<FileSystem>().unwrap();
+        assert_eq!(fs.total_size("/"), 325);
+        assert_eq!(fs.total_size("//a"), 325);
+        assert!(!fs.dirs.contains_key("//a/b/c"));
+    }
+
+    #[test]
+    fn test_rejects_bad_transcripts() {
+        assert!("$ cd /\n$ rm missing".parse::<FileSystem>().is_err());
+        assert!("$ frobnicate".parse::<Token>().is_err());
+        assert!("12 'unterminated".parse::<Token>().is_err());
+    }
+
+    /// A scratch directory under the system temp dir, removed on drop.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("aoc_2022-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            ScratchDir(path)
+        }
+
+        fn write(&self, path: &str, size: usize) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, vec![b'x'; size]).unwrap();
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn disk_size(path: &Path) -> usize {
+        fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .map(|entry| match entry.metadata().unwrap() {
+                m if m.is_dir() => disk_size(&entry.path()),
+                m => m.len() as usize,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_from_dir_round_trip() {
+        let scratch = ScratchDir::new("from-dir-round-trip");
+        scratch.write("a.txt", 123);
+        scratch.write("sub dir/b \"quoted\".txt", 4567);
+        scratch.write("sub dir/nested/c", 89);
+        fs::create_dir(scratch.0.join("empty")).unwrap();
+
+        let from_disk = FileSystem::from_dir(&scratch.0, Symlinks::Skip).unwrap();
+        assert_eq!(from_disk.total_size("/"), disk_size(&scratch.0));
+        assert_eq!(
+            from_disk.total_size("//sub dir"),
+            disk_size(&scratch.0.join("sub dir"))
+        );
+
+        let reparsed = from_disk.transcript().parse::<FileSystem>().unwrap();
+        assert_eq!(reparsed.transcript(), from_disk.transcript());
+        for dir in from_disk.dirs.keys() {
+            assert_eq!(reparsed.total_size(dir), from_disk.total_size(dir));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_from_dir_symlinks() {
+        let scratch = ScratchDir::new("from-dir-symlinks");
+        scratch.write("real/file", 10);
+        std::os::unix::fs::symlink(scratch.0.join("real"), scratch.0.join("real/loop")).unwrap();
+        std::os::unix::fs::symlink(scratch.0.join("real/file"), scratch.0.join("alias")).unwrap();
+
+        let skipped = FileSystem::from_dir(&scratch.0, Symlinks::Skip).unwrap();
+        assert_eq!(skipped.total_size("/"), 10);
+
+        let followed = FileSystem::from_dir(&scratch.0, Symlinks::Follow).unwrap();
+        assert_eq!(followed.total_size("/"), 20);
     }
 }
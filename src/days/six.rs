@@ -1,33 +1,323 @@
-use std::collections::VecDeque;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{self, Read},
+    ops::Range,
+};
 
+use anyhow::Context;
 use itertools::Itertools;
 
-use super::Day;
+use super::{Day, Puzzle};
+use crate::{answer::Answer, parse::LineErrors, random::Rng};
 
-pub const DAY_SIX: Day = Day { part_one, part_two };
+pub const DAY_SIX: Day = Day::new::<DaySix>();
 
-fn part_one(input: &str) -> String {
-    let mut buf = input.chars().take(4).collect::<VecDeque<_>>();
-    let mut total_taken = 4;
-    let mut chars = input.chars().skip(4);
-    while !buf.iter().all_unique() {
-        buf.pop_front();
-        buf.push_back(chars.next().unwrap());
-        total_taken += 1;
+const START_OF_PACKET: usize = 4;
+const START_OF_MESSAGE: usize = 14;
+
+/// Incremental search for the first run of `window` distinct bytes.
+///
+/// Each byte's last position is remembered, so a repeat moves the start of the
+/// candidate window straight past the earlier copy instead of rescanning it.
+struct MarkerScanner {
+    window: usize,
+    /// One past the index where each byte was last seen, 0 if never seen.
+    last_seen: [usize; 256],
+    /// Index of the first byte of the current all-distinct run.
+    start: usize,
+    consumed: usize,
+}
+
+impl MarkerScanner {
+    fn new(window: usize) -> Self {
+        MarkerScanner {
+            window,
+            last_seen: [0; 256],
+            start: 0,
+            consumed: 0,
+        }
+    }
+
+    /// Feeds one byte, returning the length of the all-distinct run that ends with it.
+    fn push(&mut self, byte: u8) -> usize {
+        let seen = &mut self.last_seen[byte as usize];
+        self.start = self.start.max(*seen);
+        self.consumed += 1;
+        *seen = self.consumed;
+        self.consumed - self.start
+    }
+
+    /// Feeds the next chunk of the stream, returning the number of bytes read
+    /// up to and including the end of the first marker once it is found.
+    fn scan(&mut self, bytes: &[u8]) -> Option<usize> {
+        if self.window == 0 {
+            return Some(0);
+        }
+        for &byte in bytes {
+            if self.push(byte) == self.window {
+                return Some(self.consumed);
+            }
+        }
+        None
+    }
+}
+
+/// Position just after the first `window` consecutive distinct bytes, if any.
+pub fn find_marker(input: &[u8], window: usize) -> Option<usize> {
+    MarkerScanner::new(window).scan(input)
+}
+
+/// Like [`find_marker`], but reads the datastream incrementally so it never
+/// has to be held in memory.
+pub fn find_marker_in<R: Read>(mut reader: R, window: usize) -> io::Result<Option<usize>> {
+    let mut scanner = MarkerScanner::new(window);
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => return Ok(scanner.scan(&[])),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if let Some(marker) = scanner.scan(&buf[..read]) {
+            return Ok(Some(marker));
+        }
+    }
+}
+
+/// Fast path of [`find_marker`] for input made only of ASCII lowercase letters.
+///
+/// The window is kept as a 26-bit mask that each byte XORs in when it enters
+/// and out when it leaves. A letter seen twice cancels itself out, so the
+/// window is all distinct exactly when the mask has `window` bits set.
+pub fn find_marker_lowercase(input: &[u8], window: usize) -> Option<usize> {
+    debug_assert!(input.iter().all(u8::is_ascii_lowercase));
+    let bit = |byte: u8| 1u32 << (byte.wrapping_sub(b'a') & 31);
+
+    if window == 0 {
+        return Some(0);
+    }
+    if window > 26 || input.len() < window {
+        return None;
+    }
+
+    // `distinct` tracks the set bits of `mask` so no popcount is needed per byte.
+    let mut mask = 0;
+    let mut distinct = 0;
+    let mut toggle = |byte: u8| {
+        mask ^= bit(byte);
+        distinct += (mask & bit(byte) != 0) as i32 * 2 - 1;
+        distinct as usize
+    };
+
+    for &byte in &input[..window - 1] {
+        toggle(byte);
+    }
+    for (start, (&incoming, &outgoing)) in input[window - 1..].iter().zip(input).enumerate() {
+        if toggle(incoming) == window {
+            return Some(start + window);
+        }
+        toggle(outgoing);
+    }
+    None
+}
+
+/// Position (in chars) just after the first `window` consecutive distinct
+/// chars. Lowercase ASCII input takes the bitmask fast path; anything else is
+/// scanned char by char.
+pub fn find_marker_str(input: &str, window: usize) -> Option<usize> {
+    if input.bytes().all(|b| b.is_ascii_lowercase()) {
+        return find_marker_lowercase(input.as_bytes(), window);
+    }
+    if window == 0 {
+        return Some(0);
+    }
+
+    let mut last_seen = HashMap::new();
+    let mut start = 0;
+    for (idx, c) in input.chars().enumerate() {
+        if let Some(seen) = last_seen.insert(c, idx + 1) {
+            start = start.max(seen);
+        }
+        if idx + 1 - start == window {
+            return Some(idx + 1);
+        }
+    }
+    None
+}
+
+/// Every position that ends a run of `window` distinct bytes, markers overlapping.
+pub fn all_markers(input: &[u8], window: usize) -> Vec<usize> {
+    let mut scanner = MarkerScanner::new(window);
+    input
+        .iter()
+        .filter_map(|&byte| (scanner.push(byte) >= window).then_some(scanner.consumed))
+        .collect()
+}
+
+/// A marker and the data that follows it up to the next marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet<'a> {
+    pub marker: &'a [u8],
+    pub payload: &'a [u8],
+}
+
+/// Splits a datastream into packets, each starting at a marker. The search for
+/// the next marker restarts once the previous one ends, and bytes before the
+/// first marker are dropped.
+pub fn packets(input: &[u8], window: usize) -> Vec<Packet<'_>> {
+    let mut packets = Vec::new();
+    if window == 0 {
+        return packets;
+    }
+
+    let mut rest = input;
+    let mut marker = None;
+    loop {
+        let next = find_marker(rest, window);
+        if let Some(marker) = marker {
+            let payload_end = next.map_or(rest.len(), |end| end - window);
+            packets.push(Packet {
+                marker,
+                payload: &rest[..payload_end],
+            });
+        }
+        match next {
+            Some(end) => {
+                marker = Some(&rest[end - window..end]);
+                rest = &rest[end..];
+            }
+            None => return packets,
+        }
+    }
+}
+
+/// The longest run of distinct bytes, as a range of positions.
+pub fn longest_distinct_run(input: &[u8]) -> Range<usize> {
+    let mut scanner = MarkerScanner::new(usize::MAX);
+    let mut longest = 0..0;
+    for &byte in input {
+        let run = scanner.push(byte);
+        if run > longest.len() {
+            longest = scanner.consumed - run..scanner.consumed;
+        }
+    }
+    longest
+}
+
+/// Summary of the markers of one window size in a datastream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkerReport {
+    pub window: usize,
+    pub markers: Vec<usize>,
+    pub packets: usize,
+    pub longest_run: Range<usize>,
+}
+
+impl MarkerReport {
+    pub fn new(input: &[u8], window: usize) -> Self {
+        MarkerReport {
+            window,
+            markers: all_markers(input, window),
+            packets: packets(input, window).len(),
+            longest_run: longest_distinct_run(input),
+        }
     }
-    total_taken.to_string()
 }
 
-fn part_two(input: &str) -> String {
-    let mut buf = input.chars().take(14).collect::<VecDeque<_>>();
-    let mut total_taken = 14;
-    let mut chars = input.chars().skip(14);
-    while !buf.iter().all_unique() {
-        buf.pop_front();
-        buf.push_back(chars.next().unwrap());
-        total_taken += 1;
+impl Display for MarkerReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Window size: {}", self.window)?;
+        match self.markers.first() {
+            Some(first) => writeln!(f, "First marker ends at: {}", first)?,
+            None => writeln!(f, "First marker ends at: none")?,
+        }
+        writeln!(f, "Marker positions: {}", self.markers.len())?;
+        writeln!(f, "Packets: {}", self.packets)?;
+        write!(
+            f,
+            "Longest distinct run: {}..{} ({} bytes)",
+            self.longest_run.start,
+            self.longest_run.end,
+            self.longest_run.len()
+        )
+    }
+}
+
+/// A datastream of `len` lowercase letters whose first marker of `window`
+/// letters ends at `marker`. Before the marker, every run of `window - 1`
+/// distinct letters is followed by a repeat of one of them.
+pub fn generate_datastream(rng: &mut Rng, len: usize, window: usize, marker: usize) -> String {
+    // Any single letter is a marker, so a window of one cannot be delayed.
+    assert!((2..=26).contains(&window), "window must be 2 to 26 letters");
+    assert!(
+        (window..=len).contains(&marker),
+        "marker must end between the window and the end of the datastream"
+    );
+    let letters = (b'a'..=b'z').collect::<Vec<_>>();
+    let marker_start = marker - window;
+
+    let mut stream: Vec<u8> = Vec::with_capacity(len);
+    for idx in 0..len {
+        // The letters sharing a window with this one, if it ends one.
+        let before = &stream[(idx + 1).saturating_sub(window)..idx];
+        let ends_distinct = idx + 1 >= window && before.iter().all_unique();
+
+        let next = if idx < marker_start {
+            match ends_distinct {
+                true => *rng.choose(before),
+                false => *rng.choose(&letters),
+            }
+        } else if idx < marker {
+            let placed = &stream[marker_start..idx];
+            // Only the final window may be distinct. Before that, repeat a
+            // letter from ahead of the marker that is not part of it.
+            let candidates = match ends_distinct && idx + 1 < marker {
+                true => &before[..before.len() - placed.len()],
+                false => &letters[..],
+            };
+            let candidates = candidates
+                .iter()
+                .filter(|c| !placed.contains(c))
+                .collect::<Vec<_>>();
+            **rng.choose(&candidates)
+        } else {
+            *rng.choose(&letters)
+        };
+        stream.push(next);
+    }
+    String::from_utf8(stream).unwrap()
+}
+
+struct DaySix;
+
+impl Puzzle for DaySix {
+    type Input = String;
+
+    fn parse(input: &str, _errors: &mut LineErrors) -> anyhow::Result<String> {
+        Ok(input.to_string())
+    }
+
+    fn part_one(datastream: &String) -> anyhow::Result<Answer> {
+        Ok(find_marker_str(datastream, START_OF_PACKET)
+            .context("datastream has no start-of-packet marker")?
+            .into())
+    }
+
+    fn part_two(datastream: &String) -> anyhow::Result<Answer> {
+        Ok(find_marker_str(datastream, START_OF_MESSAGE)
+            .context("datastream has no start-of-message marker")?
+            .into())
+    }
+
+    /// `size` letters, at least 14, with the start-of-message marker ending
+    /// at a random position.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let len = size.max(START_OF_MESSAGE);
+        let marker = rng.range(START_OF_MESSAGE as u64..=len as u64) as usize;
+        generate_datastream(rng, len, START_OF_MESSAGE, marker) + "\n"
     }
-    total_taken.to_string()
 }
 
 #[cfg(test)]
@@ -37,28 +327,137 @@ mod tests {
     #[test]
     fn test_part_one_example() {
         let example = include_str!("../../inputs/6/1/example.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "7");
+        let answer = (DAY_SIX.part_one)(example);
+        assert_eq!(answer, Answer::Unsigned(7));
     }
 
     #[test]
     fn test_part_one_input() {
         let example = include_str!("../../inputs/6/1/input.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "1175");
+        let answer = (DAY_SIX.part_one)(example);
+        assert_eq!(answer, Answer::Unsigned(1175));
     }
 
     #[test]
     fn test_part_two_example() {
         let example = include_str!("../../inputs/6/2/example.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "19");
+        let answer = (DAY_SIX.part_two)(example);
+        assert_eq!(answer, Answer::Unsigned(19));
     }
 
     #[test]
     fn test_part_two_input() {
         let example = include_str!("../../inputs/6/2/input.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "3217");
+        let answer = (DAY_SIX.part_two)(example);
+        assert_eq!(answer, Answer::Unsigned(3217));
+    }
+
+    #[test]
+    fn test_find_marker() {
+        let cases = [
+            ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+        ];
+        for (input, packet, message) in cases {
+            assert_eq!(find_marker(input.as_bytes(), 4), Some(packet));
+            assert_eq!(find_marker(input.as_bytes(), 14), Some(message));
+        }
+        assert_eq!(find_marker(b"aaaaaaaa", 2), None);
+        assert_eq!(find_marker(b"abc", 4), None);
+        assert_eq!(find_marker(b"", 0), Some(0));
+    }
+
+    /// Hands out one byte per read to exercise marker detection across chunks.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((&byte, rest)), Some(slot)) => {
+                    *slot = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_marker_in_reader() {
+        let input = include_bytes!("../../inputs/6/2/input.txt");
+        assert_eq!(find_marker_in(Trickle(input), 4).unwrap(), Some(1175));
+        assert_eq!(find_marker_in(&input[..], 14).unwrap(), Some(3217));
+        assert_eq!(find_marker_in(Trickle(b"abab"), 3).unwrap(), None);
+    }
+
+    #[test]
+    fn test_all_markers() {
+        assert_eq!(all_markers(b"aabcdd", 3), vec![4, 5]);
+        assert_eq!(all_markers(b"abcd", 1), vec![1, 2, 3, 4]);
+        assert!(all_markers(b"abab", 3).is_empty());
+    }
+
+    #[test]
+    fn test_packets() {
+        let packets = packets(b"aaabcddddefffff", 3);
+        assert_eq!(
+            packets,
+            vec![
+                Packet {
+                    marker: b"abc",
+                    payload: b"ddd"
+                },
+                Packet {
+                    marker: b"def",
+                    payload: b"ffff"
+                },
+            ]
+        );
+        assert_eq!(super::packets(b"aaaa", 2), vec![]);
+    }
+
+    #[test]
+    fn test_longest_distinct_run() {
+        assert_eq!(longest_distinct_run(b"abcabcdab"), 3..7);
+        assert_eq!(longest_distinct_run(b""), 0..0);
+        let report = MarkerReport::new(b"mjqjpqmgbljsphdztnvjfqwrcgsmlb", 14);
+        assert_eq!(report.markers.first(), Some(&19));
+        assert_eq!(report.longest_run, 12..30);
+    }
+
+    #[test]
+    fn test_find_marker_lowercase_matches_scanner() {
+        let input = include_str!("../../inputs/6/1/input.txt").trim_end();
+        for window in 0..=27 {
+            assert_eq!(
+                find_marker_lowercase(input.as_bytes(), window),
+                find_marker(input.as_bytes(), window),
+                "window {}",
+                window
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_datastream() {
+        let mut rng = Rng::new(6);
+        for window in [2, 4, 14, 26] {
+            for marker in [window, window + 1, 40, 100] {
+                let stream = generate_datastream(&mut rng, 100, window, marker);
+                assert_eq!(stream.len(), 100);
+                assert_eq!(find_marker(stream.as_bytes(), window), Some(marker));
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_marker_str_unicode() {
+        assert_eq!(find_marker_str("ééaéßü", 3), Some(5));
+        assert_eq!(find_marker_str("Ab Ab", 3), Some(3));
+        assert_eq!(find_marker_str("ééé", 2), None);
     }
 }
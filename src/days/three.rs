@@ -1,33 +1,143 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{ops::RangeInclusive, str::FromStr};
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 use itertools::Itertools;
 
-use super::Day;
+use super::{Day, Puzzle};
+use crate::{
+    answer::Answer,
+    parse::{parse_lines, LineErrors},
+    random::Rng,
+};
 
-pub const DAY_THREE: Day = Day { part_one, part_two };
+pub const DAY_THREE: Day = Day::new::<DayThree>();
 
-type Compartment = HashSet<char>;
+/// The items that may be packed, in priority order starting at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alphabet {
+    items: Vec<char>,
+    /// Priorities of the ASCII items, 0 where the character is not an item.
+    ascii: [u8; 128],
+}
+
+impl Alphabet {
+    /// At most 63 distinct items fit in a [`Compartment`].
+    pub fn new(items: &str) -> anyhow::Result<Self> {
+        let items = items.chars().collect::<Vec<_>>();
+        if items.len() > 63 {
+            bail!("An alphabet holds at most 63 items, got {}", items.len());
+        }
+        if let Some(item) = items.iter().duplicates().next() {
+            bail!("Item ({}) appears more than once in the alphabet", item);
+        }
+        let mut ascii = [0; 128];
+        for (idx, item) in items.iter().enumerate() {
+            if item.is_ascii() {
+                ascii[*item as usize] = idx as u8 + 1;
+            }
+        }
+        Ok(Alphabet { items, ascii })
+    }
 
-struct RuckSack {
-    a: Compartment,
-    b: Compartment,
+    /// `a` to `z` followed by `A` to `Z`, as in the puzzle.
+    pub fn letters() -> Self {
+        Alphabet::new("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ").unwrap()
+    }
+
+    pub fn priority(&self, item: char) -> Option<i32> {
+        if item.is_ascii() {
+            let priority = self.ascii[item as usize];
+            return (priority > 0).then_some(priority as i32);
+        }
+        self.items
+            .iter()
+            .position(|&c| c == item)
+            .map(|idx| idx as i32 + 1)
+    }
+
+    pub fn item(&self, priority: i32) -> Option<char> {
+        let idx = usize::try_from(priority).ok()?.checked_sub(1)?;
+        self.items.get(idx).copied()
+    }
 }
 
-trait Priority {
-    fn priority(&self) -> i32;
+impl Default for Alphabet {
+    fn default() -> Self {
+        Alphabet::letters()
+    }
 }
 
-impl Priority for char {
-    fn priority(&self) -> i32 {
-        if !self.is_alphabetic() {
-            panic!("Not alphabetic");
+/// A set of items stored as a bitmask, with bit `n` set for the item of priority `n`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Compartment(u64);
+
+impl Compartment {
+    pub fn parse(items: &str, alphabet: &Alphabet) -> anyhow::Result<Self> {
+        let mut bits = 0;
+        for (idx, item) in items.chars().enumerate() {
+            let priority = alphabet.priority(item).with_context(|| {
+                format!(
+                    "Invalid item ({}) at position {} of {}",
+                    item,
+                    idx + 1,
+                    items
+                )
+            })?;
+            bits |= 1 << priority;
         }
+        Ok(Compartment(bits))
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Compartment(self.0 & other.0)
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Compartment(self.0 | other.0)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The number of distinct items.
+    pub fn len(self) -> u32 {
+        self.0.count_ones()
+    }
 
-        if self.is_lowercase() {
-            (*self as i32) - ('a' as i32) + 1
-        } else {
-            (*self as i32) - ('A' as i32) + 27
+    /// The priority of the lowest-priority item, if any.
+    pub fn priority(self) -> Option<i32> {
+        (!self.is_empty()).then(|| self.0.trailing_zeros() as i32)
+    }
+
+    /// The priority of every item, lowest first.
+    pub fn priorities(self) -> impl Iterator<Item = i32> {
+        let mut bits = self.0;
+        std::iter::from_fn(move || {
+            let priority = bits.trailing_zeros();
+            bits &= bits.checked_sub(1)?;
+            Some(priority as i32)
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RuckSack {
+    pub a: Compartment,
+    pub b: Compartment,
+}
+
+pub trait Priority {
+    /// `None` for anything but the ASCII letters.
+    fn priority(&self) -> Option<i32>;
+}
+
+impl Priority for char {
+    fn priority(&self) -> Option<i32> {
+        match self {
+            'a'..='z' => Some((*self as i32) - ('a' as i32) + 1),
+            'A'..='Z' => Some((*self as i32) - ('A' as i32) + 27),
+            _ => None,
         }
     }
 }
@@ -36,78 +146,362 @@ impl FromStr for RuckSack {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() % 2 != 0 {
+        RuckSack::parse(s, &Alphabet::letters())
+    }
+}
+
+impl RuckSack {
+    pub fn parse(s: &str, alphabet: &Alphabet) -> anyhow::Result<Self> {
+        let len = s.chars().count();
+        if !len.is_multiple_of(2) {
             bail!("Invalid Rucksack (not even) {}", s);
         }
 
-        let (a, b) = s.split_at(s.len() / 2);
+        let middle = s
+            .char_indices()
+            .nth(len / 2)
+            .map_or(s.len(), |(idx, _)| idx);
+        let (a, b) = s.split_at(middle);
 
         Ok(RuckSack {
-            a: a.chars().collect(),
-            b: b.chars().collect(),
+            a: Compartment::parse(a, alphabet)?,
+            b: Compartment::parse(b, alphabet)?,
         })
     }
-}
 
-impl RuckSack {
-    fn overlap(&self) -> HashSet<char> {
-        self.a.intersection(&self.b).cloned().collect()
+    /// Items packed in both compartments.
+    pub fn overlap(&self) -> Compartment {
+        self.a.intersection(self.b)
     }
+
+    pub fn items(&self) -> Compartment {
+        self.a.union(self.b)
+    }
+}
+
+/// Parses one rucksack per line, naming every bad line.
+pub fn parse_rucksacks(input: &str, alphabet: &Alphabet) -> anyhow::Result<Vec<RuckSack>> {
+    parse_lines(input, |line| RuckSack::parse(line, alphabet))
+}
+
+/// Parses the whole contents of one rucksack per line, ignoring compartments.
+pub fn parse_inventories(input: &str, alphabet: &Alphabet) -> anyhow::Result<Vec<Compartment>> {
+    parse_lines(input, |line| Compartment::parse(line, alphabet))
 }
 
-pub fn part_one(input: &str) -> String {
-    input
-        .lines()
-        .map(RuckSack::from_str)
-        .map(Result::unwrap)
-        .flat_map(|r| r.overlap())
-        .map(|c| c.priority())
-        .sum::<i32>()
-        .to_string()
+/// The one item carried by every elf of a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Badge {
+    /// Line numbers of the group's rucksacks.
+    pub lines: RangeInclusive<usize>,
+    pub item: char,
+    pub priority: i32,
 }
 
-pub fn part_two(input: &str) -> String {
-    input
-        .lines()
-        .map(|line| line.chars().collect::<HashSet<char>>())
-        .chunks(3)
-        .into_iter()
-        .flat_map(|chunk| chunk.reduce(|a, b| a.intersection(&b).cloned().collect()))
-        .flatten()
-        .map(|c| c.priority())
-        .sum::<i32>()
-        .to_string()
+fn describe_lines(lines: &RangeInclusive<usize>) -> String {
+    lines.clone().map(|line| line.to_string()).join(", ")
+}
+
+/// Splits the rucksacks into consecutive groups of `group_size` and finds the
+/// badge of each group. Fails on a trailing incomplete group and on groups
+/// sharing no item or more than one.
+pub fn find_badges(
+    input: &str,
+    alphabet: &Alphabet,
+    group_size: usize,
+) -> anyhow::Result<Vec<Badge>> {
+    let inventories = parse_inventories(input, alphabet)?;
+    let inventories = inventories.into_iter().map(Some).collect::<Vec<_>>();
+    group_badges(&inventories, alphabet, group_size)
+}
+
+/// The same as `find_badges`, for rucksacks that have already been parsed, one
+/// per line counting from 1. `None` stands for a line that was skipped as
+/// invalid, and the group it belongs to is left out.
+pub fn group_badges(
+    inventories: &[Option<Compartment>],
+    alphabet: &Alphabet,
+    group_size: usize,
+) -> anyhow::Result<Vec<Badge>> {
+    if group_size == 0 {
+        bail!("Groups must have at least one elf");
+    }
+
+    inventories
+        .chunks(group_size)
+        .enumerate()
+        .filter(|(_, group)| group.iter().all(Option::is_some))
+        .map(|(idx, group)| {
+            let first_line = idx * group_size + 1;
+            let lines = first_line..=first_line + group.len() - 1;
+
+            if group.len() < group_size {
+                bail!(
+                    "Incomplete group on lines {}, expected {} elves",
+                    describe_lines(&lines),
+                    group_size
+                );
+            }
+
+            let common = group
+                .iter()
+                .flatten()
+                .copied()
+                .reduce(Compartment::intersection)
+                .unwrap_or_default();
+            match common.len() {
+                1 => {
+                    let priority = common.priority().unwrap();
+                    Ok(Badge {
+                        lines,
+                        item: alphabet.item(priority).unwrap(),
+                        priority,
+                    })
+                }
+                0 => bail!(
+                    "No common item in group on lines {}",
+                    describe_lines(&lines)
+                ),
+                _ => bail!(
+                    "Ambiguous badge ({}) in group on lines {}",
+                    common
+                        .priorities()
+                        .filter_map(|p| alphabet.item(p))
+                        .join(", "),
+                    describe_lines(&lines)
+                ),
+            }
+        })
+        .collect()
+}
+
+struct DayThree;
+
+impl Puzzle for DayThree {
+    /// One entry per line, `None` where a line was skipped, so that groups in
+    /// part two stay on the lines they were read from.
+    type Input = Vec<Option<RuckSack>>;
+
+    fn parse(input: &str, errors: &mut LineErrors) -> anyhow::Result<Vec<Option<RuckSack>>> {
+        let alphabet = Alphabet::letters();
+        Ok(input
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| match RuckSack::parse(line, &alphabet) {
+                Ok(rucksack) => Some(rucksack),
+                Err(error) => {
+                    errors.push(idx + 1, error);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    fn part_one(rucksacks: &Vec<Option<RuckSack>>) -> anyhow::Result<Answer> {
+        Ok(rucksacks
+            .iter()
+            .flatten()
+            .flat_map(|r| r.overlap().priorities())
+            .sum::<i32>()
+            .into())
+    }
+
+    fn part_two(rucksacks: &Vec<Option<RuckSack>>) -> anyhow::Result<Answer> {
+        let inventories = rucksacks
+            .iter()
+            .map(|rucksack| rucksack.as_ref().map(RuckSack::items))
+            .collect::<Vec<_>>();
+        Ok(group_badges(&inventories, &Alphabet::letters(), 3)?
+            .iter()
+            .map(|badge| badge.priority)
+            .sum::<i32>()
+            .into())
+    }
+
+    /// `size` rucksacks, rounded up to whole groups of three, of 8 to 23 items
+    /// per compartment. Exactly one item is in both compartments and exactly
+    /// one badge is shared by each group.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut letters = *b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+        let mut input = String::new();
+        for _ in 0..size.div_ceil(3) {
+            rng.shuffle(&mut letters);
+            // Each elf draws from its own 17 letters, so only the badge is shared.
+            let (badge, pools) = (letters[0], &letters[1..]);
+            for pool in pools.chunks(17) {
+                let (left, right, shared) = (&pool[..8], &pool[8..16], pool[16]);
+                let len = rng.range(8..=23);
+                let mut a = vec![badge, shared];
+                a.extend((2..len).map(|_| *rng.choose(left)));
+                let mut b = vec![shared];
+                b.extend((1..len).map(|_| *rng.choose(right)));
+                for compartment in [&mut a, &mut b] {
+                    rng.shuffle(compartment);
+                    input.extend(compartment.iter().map(|&item| item as char));
+                }
+                input.push('\n');
+            }
+        }
+        input
+    }
 }
 
 #[cfg(test)]
 mod tests {
     pub use super::*;
+    use crate::days::RunOptions;
 
     #[test]
     fn test_part_one_example() {
         let example = include_str!("../../inputs/3/1/example.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "157");
+        let answer = (DAY_THREE.part_one)(example);
+        assert_eq!(answer, Answer::Integer(157));
     }
 
     #[test]
     fn test_part_one_input() {
         let example = include_str!("../../inputs/3/1/input.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "7581");
+        let answer = (DAY_THREE.part_one)(example);
+        assert_eq!(answer, Answer::Integer(7581));
     }
 
     #[test]
     fn test_part_two_example() {
         let example = include_str!("../../inputs/3/2/example.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "70");
+        let answer = (DAY_THREE.part_two)(example);
+        assert_eq!(answer, Answer::Integer(70));
     }
 
     #[test]
     fn test_part_two_input() {
         let example = include_str!("../../inputs/3/2/input.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "2525");
+        let answer = (DAY_THREE.part_two)(example);
+        assert_eq!(answer, Answer::Integer(2525));
+    }
+
+    #[test]
+    fn test_compartment() {
+        let letters = Alphabet::letters();
+        let sack = "vJrwpWtwJgWrhcsFMMfFFhFp".parse::<RuckSack>().unwrap();
+        assert_eq!(sack.overlap(), Compartment::parse("p", &letters).unwrap());
+        assert_eq!(sack.overlap().priority(), Some(16));
+
+        let items = Compartment::parse("aAzZa", &letters).unwrap();
+        assert_eq!(items.len(), 4);
+        assert_eq!(items.priorities().collect::<Vec<_>>(), vec![1, 26, 27, 52]);
+        assert!(Compartment::default().is_empty());
+        assert_eq!(Compartment::default().priority(), None);
+        assert_eq!(Compartment::default().priorities().count(), 0);
+    }
+
+    #[test]
+    fn test_invalid_items() {
+        assert_eq!('é'.priority(), None);
+        assert_eq!('1'.priority(), None);
+        assert_eq!('Z'.priority(), Some(52));
+
+        let error = parse_rucksacks("abab\naéaé\n", &Alphabet::letters()).unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(
+            message.starts_with("line 2: Invalid item (é) at position 2"),
+            "{}",
+            message
+        );
+        assert!("a1a1".parse::<RuckSack>().is_err());
+    }
+
+    #[test]
+    fn test_custom_alphabet() {
+        let alphabet =
+            Alphabet::new("0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ")
+                .unwrap();
+        assert_eq!(alphabet.priority('0'), Some(1));
+        assert_eq!(alphabet.priority('Z'), Some(62));
+        assert_eq!(alphabet.item(11), Some('a'));
+        assert_eq!(alphabet.item(0), None);
+
+        let sack = RuckSack::parse("12a9xa", &alphabet).unwrap();
+        assert_eq!(sack.overlap().priorities().collect::<Vec<_>>(), vec![11]);
+
+        let accented = Alphabet::new("éèê").unwrap();
+        let sack = RuckSack::parse("éèêé", &accented).unwrap();
+        assert_eq!(sack.overlap().priority(), Some(1));
+
+        assert!(Alphabet::new("abca").is_err());
+        assert!(Alphabet::new(&"x".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_find_badges() {
+        let example = include_str!("../../inputs/3/2/example.txt");
+        let badges = find_badges(example, &Alphabet::letters(), 3).unwrap();
+        assert_eq!(
+            badges,
+            vec![
+                Badge {
+                    lines: 1..=3,
+                    item: 'r',
+                    priority: 18
+                },
+                Badge {
+                    lines: 4..=6,
+                    item: 'Z',
+                    priority: 52
+                },
+            ]
+        );
+
+        let pairs = find_badges(example, &Alphabet::letters(), 2);
+        assert_eq!(
+            format!("{:#}", pairs.unwrap_err()),
+            "Ambiguous badge (f, r, s, F, M) in group on lines 1, 2"
+        );
+        let errors = [
+            (
+                "abc\nabd\nabe\nxy",
+                "Ambiguous badge (a, b) in group on lines 1, 2, 3",
+            ),
+            ("abc\nabd\nxyz", "No common item in group on lines 1, 2, 3"),
+            (
+                "ab\nbc\nbd\nab\nb",
+                "Incomplete group on lines 4, 5, expected 3 elves",
+            ),
+        ];
+        for (input, error) in errors {
+            let result = find_badges(input, &Alphabet::letters(), 3);
+            assert_eq!(format!("{:#}", result.unwrap_err()), error);
+        }
+        assert!(find_badges("ab", &Alphabet::letters(), 0).is_err());
+    }
+
+    #[test]
+    fn test_lenient_groups() {
+        let example = include_str!("../../inputs/3/2/example.txt");
+        // An odd number of items makes line 2, in the first group, invalid.
+        let input = example.replacen("jqHRNqRjqzjGDLGL", "jqHRNqRjqzjGDLG", 1);
+        assert_ne!(input, example);
+
+        let strict = (DAY_THREE.run)(&input, &[1, 2], RunOptions::default());
+        assert!(format!("{:#}", strict.unwrap_err()).contains("line 2"));
+
+        let options = RunOptions {
+            lenient: true,
+            ..RunOptions::default()
+        };
+        let run = (DAY_THREE.run)(&input, &[1, 2], options).unwrap();
+        assert_eq!(
+            run.skipped
+                .iter()
+                .map(|(line, _)| *line)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+        // Line 2 shares L between its compartments, and only the second
+        // group's badge, Z, is left.
+        let answers = run.parts.iter().map(|(_, answer, _)| answer.clone());
+        assert_eq!(
+            answers.collect::<Vec<_>>(),
+            vec![Answer::Integer(157 - 38), Answer::Integer(52)]
+        );
     }
 }
@@ -1,188 +1,634 @@
-use std::str::FromStr;
+use std::collections::{BTreeSet, HashMap};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail, Context};
+use itertools::Itertools;
 
-use super::Day;
+use super::{Day, Puzzle};
+use crate::{
+    answer::Answer,
+    parse::{parse_lines, split_pair, LineErrors, Template},
+    random::Rng,
+};
 
-pub const DAY_TWO: Day = Day { part_one, part_two };
+pub const DAY_TWO: Day = Day::new::<DayTwo>();
+
+/// A shape, identified by its position in the cycle of its [`Rules`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Shape(pub usize);
+
+impl Shape {
+    pub const ROCK: Shape = Shape(0);
+    pub const PAPER: Shape = Shape(1);
+    pub const SCISSORS: Shape = Shape(2);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Win,
+    Tie,
+    Lose,
+}
+
+impl Outcome {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "win" => Some(Outcome::Win),
+            "tie" | "draw" => Some(Outcome::Tie),
+            "lose" | "loss" => Some(Outcome::Lose),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum Shape {
-    Rock,
-    Paper,
-    Scissors,
+pub struct OutcomeScores {
+    pub win: i32,
+    pub tie: i32,
+    pub lose: i32,
 }
 
-impl Shape {
-    fn score(&self) -> i32 {
-        match self {
-            Shape::Rock => 1,
-            Shape::Paper => 2,
-            Shape::Scissors => 3,
+/// A rock-paper-scissors style game over an odd cycle of shapes.
+///
+/// Every shape beats the `(n - 1) / 2` shapes before it in the cycle and
+/// loses to the ones after it, so each pair of distinct shapes has a winner.
+/// Three shapes give rock, paper, scissors; five give rock, Spock, paper,
+/// lizard, scissors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    names: Vec<String>,
+    shape_scores: Vec<i32>,
+    outcome_scores: OutcomeScores,
+}
+
+impl Rules {
+    /// `shapes` lists each shape's name and score in cycle order. At least
+    /// three are needed so that every shape can both win and lose.
+    pub fn cyclic(shapes: &[(&str, i32)], outcome_scores: OutcomeScores) -> anyhow::Result<Self> {
+        if shapes.len() < 3 {
+            bail!(
+                "A cycle needs at least 3 shapes to win and lose, got {}",
+                shapes.len()
+            );
+        }
+        if shapes.len().is_multiple_of(2) {
+            bail!(
+                "A fair cycle needs an odd number of shapes, got {}",
+                shapes.len()
+            );
         }
+        Ok(Rules {
+            names: shapes.iter().map(|(name, _)| name.to_string()).collect(),
+            shape_scores: shapes.iter().map(|(_, score)| *score).collect(),
+            outcome_scores,
+        })
+    }
+
+    /// The rules of the puzzle.
+    pub fn rock_paper_scissors() -> Self {
+        Rules::cyclic(
+            &[("Rock", 1), ("Paper", 2), ("Scissors", 3)],
+            OutcomeScores {
+                win: 6,
+                tie: 3,
+                lose: 0,
+            },
+        )
+        .unwrap()
+    }
+
+    pub fn rock_paper_scissors_lizard_spock() -> Self {
+        Rules::cyclic(
+            &[
+                ("Rock", 1),
+                ("Spock", 2),
+                ("Paper", 3),
+                ("Lizard", 4),
+                ("Scissors", 5),
+            ],
+            OutcomeScores {
+                win: 6,
+                tie: 3,
+                lose: 0,
+            },
+        )
+        .unwrap()
+    }
+
+    pub fn shapes(&self) -> impl Iterator<Item = Shape> {
+        (0..self.names.len()).map(Shape)
+    }
+
+    pub fn name(&self, shape: Shape) -> &str {
+        &self.names[shape.0]
+    }
+
+    /// Looks a shape up by name, ignoring case.
+    pub fn shape(&self, name: &str) -> Option<Shape> {
+        self.names
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name))
+            .map(Shape)
+    }
+
+    pub fn shape_score(&self, shape: Shape) -> i32 {
+        self.shape_scores[shape.0]
+    }
+
+    pub fn outcome_score(&self, outcome: Outcome) -> i32 {
+        match outcome {
+            Outcome::Win => self.outcome_scores.win,
+            Outcome::Tie => self.outcome_scores.tie,
+            Outcome::Lose => self.outcome_scores.lose,
+        }
+    }
+
+    pub fn outcome(&self, mine: Shape, theirs: Shape) -> Outcome {
+        let len = self.names.len();
+        match (mine.0 + len - theirs.0) % len {
+            0 => Outcome::Tie,
+            ahead if ahead <= len / 2 => Outcome::Win,
+            _ => Outcome::Lose,
+        }
+    }
+
+    /// The shape to play against `theirs` to get `outcome`. When several
+    /// shapes would do, the one scoring the most is picked.
+    pub fn shape_for(&self, theirs: Shape, outcome: Outcome) -> Shape {
+        self.shapes()
+            .filter(|&mine| self.outcome(mine, theirs) == outcome)
+            .max_by_key(|&mine| (self.shape_score(mine), std::cmp::Reverse(mine)))
+            .unwrap()
     }
 }
 
-impl FromStr for Shape {
-    type Err = anyhow::Error;
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Game {
+    pub their_move: Shape,
+    pub my_move: Shape,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.trim() {
-            "A" => Ok(Shape::Rock),
-            "B" => Ok(Shape::Paper),
-            "C" => Ok(Shape::Scissors),
-            "X" => Ok(Shape::Rock),
-            "Y" => Ok(Shape::Paper),
-            "Z" => Ok(Shape::Scissors),
-            _ => Err(anyhow!("Invalid shape: {}", s)),
+impl Game {
+    /// Plays `plan` under `rules`.
+    pub fn from_plan(plan: &Plan, rules: &Rules) -> Self {
+        Game {
+            their_move: plan.their_move,
+            my_move: rules.shape_for(plan.their_move, plan.desired_outcome),
         }
     }
+
+    pub fn outcome(&self, rules: &Rules) -> Outcome {
+        rules.outcome(self.my_move, self.their_move)
+    }
+
+    pub fn outcome_score(&self, rules: &Rules) -> i32 {
+        rules.outcome_score(self.outcome(rules))
+    }
+
+    pub fn score(&self, rules: &Rules) -> i32 {
+        rules.shape_score(self.my_move) + self.outcome_score(rules)
+    }
 }
 
-struct Game {
-    their_move: Shape,
-    my_move: Shape,
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub their_move: Shape,
+    pub desired_outcome: Outcome,
 }
 
-impl FromStr for Game {
-    type Err = anyhow::Error;
+/// What a symbol in the second column of a strategy guide asks for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Response {
+    Shape(Shape),
+    Outcome(Outcome),
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split_whitespace();
-        let their_move = parts
-            .next()
-            .ok_or_else(|| anyhow!("Invalid shape {}", s))?
-            .parse()?;
-        let my_move = parts
-            .next()
-            .ok_or_else(|| anyhow!("Invalid shape {}", s))?
-            .parse()?;
-        if parts.next().is_some() {
-            return Err(anyhow!("Invalid game: {}", s));
+/// One line of a strategy guide.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Round {
+    pub their_move: Shape,
+    pub response: Response,
+}
+
+impl Round {
+    pub fn game(&self, rules: &Rules) -> Game {
+        match self.response {
+            Response::Shape(my_move) => Game {
+                their_move: self.their_move,
+                my_move,
+            },
+            Response::Outcome(desired_outcome) => Game::from_plan(
+                &Plan {
+                    their_move: self.their_move,
+                    desired_outcome,
+                },
+                rules,
+            ),
         }
-        Ok(Game {
-            their_move,
-            my_move,
-        })
     }
 }
 
-impl Game {
-    fn ties(&self) -> bool {
-        self.their_move == self.my_move
+/// The meaning of each symbol in the two columns of a strategy guide.
+///
+/// As text, an encoding is one `their SYMBOL = SHAPE` or
+/// `my SYMBOL = SHAPE|win|tie|lose` entry per line, with `#` comments:
+///
+/// ```text
+/// their A = Rock
+/// my X = lose
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Encoding {
+    theirs: HashMap<String, Shape>,
+    mine: HashMap<String, Response>,
+}
+
+/// Two columns of symbols, theirs then mine.
+const ROUND: Template = Template::new("{} {}");
+
+impl Encoding {
+    /// Both columns as shapes, as in part one. A, B, C and X, Y, Z stand for
+    /// the shapes of `rules` named Rock, Paper and Scissors.
+    pub fn shapes(rules: &Rules) -> Self {
+        let mut encoding = Encoding::default();
+        let symbols = ["A", "B", "C"].into_iter().zip(["X", "Y", "Z"]);
+        for ((theirs, mine), name) in symbols.zip(["Rock", "Paper", "Scissors"]) {
+            if let Some(shape) = rules.shape(name) {
+                encoding.theirs.insert(theirs.to_string(), shape);
+                encoding
+                    .mine
+                    .insert(mine.to_string(), Response::Shape(shape));
+            }
+        }
+        encoding
     }
 
-    fn i_win(&self) -> bool {
-        match self.their_move {
-            Shape::Rock => self.my_move == Shape::Paper,
-            Shape::Paper => self.my_move == Shape::Scissors,
-            Shape::Scissors => self.my_move == Shape::Rock,
+    /// The second column as outcomes, as in part two.
+    pub fn outcomes(rules: &Rules) -> Self {
+        let outcomes = [Outcome::Lose, Outcome::Tie, Outcome::Win];
+        Encoding {
+            mine: ["X", "Y", "Z"]
+                .map(String::from)
+                .into_iter()
+                .zip(outcomes.map(Response::Outcome))
+                .collect(),
+            ..Encoding::shapes(rules)
         }
     }
 
-    fn outcome_score(&self) -> i32 {
-        if self.ties() {
-            3
-        } else if self.i_win() {
-            6
-        } else {
-            0
+    pub fn parse(text: &str, rules: &Rules) -> anyhow::Result<Self> {
+        let mut encoding = Encoding::default();
+        parse_lines(text, |line| {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                return Ok(());
+            }
+            let (column, entry) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match column {
+                "their" => encoding.insert_theirs(entry, rules),
+                "my" => encoding.insert_mine(entry, rules),
+                _ => Err(anyhow!("Expected an entry for 'their' or 'my' column")),
+            }
+            .with_context(|| line.to_string())
+        })?;
+        Ok(encoding)
+    }
+
+    /// Replaces the first column with comma-separated `SYMBOL=SHAPE` entries.
+    pub fn set_theirs(&mut self, entries: &str, rules: &Rules) -> anyhow::Result<()> {
+        self.theirs.clear();
+        entries
+            .split(',')
+            .try_for_each(|entry| self.insert_theirs(entry, rules))
+    }
+
+    /// Replaces the second column with comma-separated `SYMBOL=SHAPE` or
+    /// `SYMBOL=OUTCOME` entries.
+    pub fn set_mine(&mut self, entries: &str, rules: &Rules) -> anyhow::Result<()> {
+        self.mine.clear();
+        entries
+            .split(',')
+            .try_for_each(|entry| self.insert_mine(entry, rules))
+    }
+
+    /// A copy of this encoding with the second column replaced by `mine`.
+    pub fn with_mine(&self, mine: impl IntoIterator<Item = (String, Response)>) -> Self {
+        Encoding {
+            theirs: self.theirs.clone(),
+            mine: mine.into_iter().collect(),
         }
     }
 
-    fn score(&self) -> i32 {
-        self.my_move.score() + self.outcome_score()
+    fn insert_theirs(&mut self, entry: &str, rules: &Rules) -> anyhow::Result<()> {
+        let (symbol, meaning) = parse_entry(entry)?;
+        let shape = rules
+            .shape(meaning)
+            .ok_or_else(|| anyhow!("Unknown shape ({})", meaning))?;
+        if self.theirs.insert(symbol.to_string(), shape).is_some() {
+            bail!("Symbol ({}) is defined twice", symbol);
+        }
+        Ok(())
+    }
+
+    fn insert_mine(&mut self, entry: &str, rules: &Rules) -> anyhow::Result<()> {
+        let (symbol, meaning) = parse_entry(entry)?;
+        let response = match (Outcome::from_name(meaning), rules.shape(meaning)) {
+            (Some(outcome), _) => Response::Outcome(outcome),
+            (None, Some(shape)) => Response::Shape(shape),
+            (None, None) => bail!("Unknown shape or outcome ({})", meaning),
+        };
+        if self.mine.insert(symbol.to_string(), response).is_some() {
+            bail!("Symbol ({}) is defined twice", symbol);
+        }
+        Ok(())
+    }
+
+    pub fn parse_round(&self, s: &str) -> anyhow::Result<Round> {
+        let fields = ROUND.fields(s)?;
+        let (theirs, mine) = (fields[0], fields[1]);
+
+        Ok(Round {
+            their_move: *self.theirs.get(theirs.text).ok_or_else(|| {
+                anyhow!("column {}: invalid shape ({})", theirs.column, theirs.text)
+            })?,
+            response: *self.mine.get(mine.text).ok_or_else(|| {
+                anyhow!("column {}: invalid response ({})", mine.column, mine.text)
+            })?,
+        })
     }
 }
 
-enum DesiredOutcome {
-    Win,
-    Tie,
-    Lose,
+fn parse_entry(entry: &str) -> anyhow::Result<(&str, &str)> {
+    let (symbol, meaning) = split_pair(entry, "=")
+        .with_context(|| format!("Expected SYMBOL = MEANING, got ({})", entry))?;
+    if symbol.is_empty() || symbol.contains(char::is_whitespace) {
+        bail!("Invalid symbol ({})", symbol);
+    }
+    Ok((symbol, meaning))
 }
 
-impl FromStr for DesiredOutcome {
-    type Err = anyhow::Error;
+/// Parses one round per line, naming every bad line.
+pub fn parse_rounds(input: &str, encoding: &Encoding) -> anyhow::Result<Vec<Round>> {
+    parse_lines(input, |line| encoding.parse_round(line))
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.trim() {
-            "Z" => Ok(DesiredOutcome::Win),
-            "Y" => Ok(DesiredOutcome::Tie),
-            "X" => Ok(DesiredOutcome::Lose),
-            _ => Err(anyhow!("Invalid desired outcome: {}", s)),
+/// The total score of following a strategy guide.
+pub fn total_score(rounds: &[Round], rules: &Rules) -> i32 {
+    rounds
+        .iter()
+        .map(|round| round.game(rules).score(rules))
+        .sum()
+}
+
+struct DayTwo;
+
+impl Puzzle for DayTwo {
+    /// The guide read with the second column as shapes, then as outcomes.
+    type Input = (Vec<Round>, Vec<Round>);
+
+    fn parse(input: &str, errors: &mut LineErrors) -> anyhow::Result<Self::Input> {
+        let rules = Rules::rock_paper_scissors();
+        let (shapes, outcomes) = (Encoding::shapes(&rules), Encoding::outcomes(&rules));
+        Ok(errors
+            .parse_lines(1, input, |line| {
+                Ok((shapes.parse_round(line)?, outcomes.parse_round(line)?))
+            })
+            .into_iter()
+            .unzip())
+    }
+
+    fn part_one((rounds, _): &Self::Input) -> anyhow::Result<Answer> {
+        Ok(total_score(rounds, &Rules::rock_paper_scissors()).into())
+    }
+
+    fn part_two((_, rounds): &Self::Input) -> anyhow::Result<Answer> {
+        Ok(total_score(rounds, &Rules::rock_paper_scissors()).into())
+    }
+
+    /// A guide of `size` rounds.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut input = String::new();
+        for _ in 0..size {
+            let theirs = rng.choose(&["A", "B", "C"]);
+            let mine = rng.choose(&["X", "Y", "Z"]);
+            input.push_str(&format!("{} {}\n", theirs, mine));
         }
+        input
     }
 }
 
-struct Plan {
-    their_move: Shape,
-    desired_outcome: DesiredOutcome,
+/// One way of reading the second column of a guide, and the score it earns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decryption {
+    pub mine: Vec<(String, Response)>,
+    pub score: i32,
 }
 
-impl FromStr for Plan {
-    type Err = anyhow::Error;
+impl Decryption {
+    pub fn describe(&self, rules: &Rules) -> String {
+        self.mine
+            .iter()
+            .map(|(symbol, response)| match response {
+                Response::Shape(shape) => format!("{}={}", symbol, rules.name(*shape)),
+                Response::Outcome(outcome) => format!("{}={:?}", symbol, outcome),
+            })
+            .join(" ")
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split_whitespace();
-        let their_move = parts
-            .next()
-            .ok_or_else(|| anyhow!("Invalid plan {}", s))?
-            .parse()?;
-        let desired_outcome = parts
-            .next()
-            .ok_or_else(|| anyhow!("Invalid plan {}", s))?
-            .parse()?;
-        if parts.next().is_some() {
-            return Err(anyhow!("Invalid plan: {}", s));
-        }
-        Ok(Plan {
-            their_move,
-            desired_outcome,
+/// Scores every way of reading the second column as distinct shapes, and
+/// every way of reading it as distinct outcomes, best first. The first column
+/// is read with `theirs`. Fails if the second column has more symbols than
+/// there are shapes to read them as.
+pub fn decryptions(
+    input: &str,
+    theirs: &Encoding,
+    rules: &Rules,
+) -> anyhow::Result<Vec<Decryption>> {
+    let symbols = parse_lines(input, |line| Ok(ROUND.fields(line)?[1].text))?
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+    let shapes = rules.shapes().count();
+    if symbols.len() > shapes {
+        bail!(
+            "The second column has {} distinct symbols, more than the {} shapes",
+            symbols.len(),
+            shapes
+        );
+    }
+
+    let as_shapes = rules
+        .shapes()
+        .map(Response::Shape)
+        .permutations(symbols.len());
+    let as_outcomes = [Outcome::Lose, Outcome::Tie, Outcome::Win]
+        .map(Response::Outcome)
+        .into_iter()
+        .permutations(symbols.len());
+
+    let mut decryptions = as_shapes
+        .chain(as_outcomes)
+        .map(|responses| {
+            let mine = symbols
+                .iter()
+                .map(|symbol| symbol.to_string())
+                .zip(responses)
+                .collect::<Vec<_>>();
+            let rounds = parse_rounds(input, &theirs.with_mine(mine.clone()))?;
+            Ok(Decryption {
+                mine,
+                score: total_score(&rounds, rules),
+            })
         })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    decryptions.sort_by_key(|decryption| std::cmp::Reverse(decryption.score));
+    Ok(decryptions)
+}
+
+/// The scoring of a single round.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RoundScore {
+    pub game: Game,
+    pub outcome: Outcome,
+    pub shape_score: i32,
+    pub outcome_score: i32,
+    pub score: i32,
+}
+
+impl RoundScore {
+    pub fn new(round: &Round, rules: &Rules) -> Self {
+        let game = round.game(rules);
+        RoundScore {
+            game,
+            outcome: game.outcome(rules),
+            shape_score: rules.shape_score(game.my_move),
+            outcome_score: game.outcome_score(rules),
+            score: game.score(rules),
+        }
     }
 }
 
-impl From<Plan> for Game {
-    fn from(plan: Plan) -> Self {
-        let my_move = match plan.desired_outcome {
-            DesiredOutcome::Win => match plan.their_move {
-                Shape::Rock => Shape::Paper,
-                Shape::Paper => Shape::Scissors,
-                Shape::Scissors => Shape::Rock,
-            },
-            DesiredOutcome::Tie => plan.their_move,
-            DesiredOutcome::Lose => match plan.their_move {
-                Shape::Rock => Shape::Scissors,
-                Shape::Paper => Shape::Rock,
-                Shape::Scissors => Shape::Paper,
-            },
+/// A tab-separated table with one row per round.
+pub fn round_table(rounds: &[Round], rules: &Rules) -> String {
+    let mut table = "round\ttheirs\tmine\toutcome\tshape score\toutcome score\tscore\n".to_string();
+    for (idx, round) in rounds.iter().enumerate() {
+        let row = RoundScore::new(round, rules);
+        table.push_str(&format!(
+            "{}\t{}\t{}\t{:?}\t{}\t{}\t{}\n",
+            idx + 1,
+            rules.name(row.game.their_move),
+            rules.name(row.game.my_move),
+            row.outcome,
+            row.shape_score,
+            row.outcome_score,
+            row.score
+        ));
+    }
+    table
+}
+
+/// Statistics over every round of a strategy guide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub wins: usize,
+    pub ties: usize,
+    pub losses: usize,
+    /// The points earned in rounds where I played each shape, in cycle order.
+    pub score_by_shape: Vec<(Shape, i32)>,
+    pub longest_winning_streak: usize,
+    /// The total score after each round.
+    pub cumulative_scores: Vec<i32>,
+}
+
+impl Report {
+    pub fn new(rounds: &[Round], rules: &Rules) -> Self {
+        let mut report = Report {
+            wins: 0,
+            ties: 0,
+            losses: 0,
+            score_by_shape: rules.shapes().map(|shape| (shape, 0)).collect(),
+            longest_winning_streak: 0,
+            cumulative_scores: Vec::with_capacity(rounds.len()),
         };
-        Game {
-            their_move: plan.their_move,
-            my_move,
+
+        let mut streak = 0;
+        let mut total = 0;
+        for round in rounds {
+            let round = RoundScore::new(round, rules);
+            match round.outcome {
+                Outcome::Win => report.wins += 1,
+                Outcome::Tie => report.ties += 1,
+                Outcome::Lose => report.losses += 1,
+            }
+            streak = if round.outcome == Outcome::Win {
+                streak + 1
+            } else {
+                0
+            };
+            report.longest_winning_streak = report.longest_winning_streak.max(streak);
+            report.score_by_shape[round.game.my_move.0].1 += round.score;
+            total += round.score;
+            report.cumulative_scores.push(total);
         }
+        report
     }
-}
 
-pub fn part_one(input: &str) -> String {
-    input
-        .lines()
-        .map(Game::from_str)
-        .map(Result::unwrap)
-        .map(|game| game.score())
-        .sum::<i32>()
-        .to_string()
+    pub fn total_score(&self) -> i32 {
+        self.cumulative_scores.last().copied().unwrap_or(0)
+    }
+
+    pub fn to_table(&self, rules: &Rules) -> String {
+        let mut table = format!(
+            "Rounds: {}\nWins: {}\nTies: {}\nLosses: {}\nLongest winning streak: {}\nTotal score: {}\n",
+            self.cumulative_scores.len(),
+            self.wins,
+            self.ties,
+            self.losses,
+            self.longest_winning_streak,
+            self.total_score()
+        );
+        table.push_str("Score by shape:\n");
+        for (shape, score) in self.score_by_shape.iter() {
+            table.push_str(&format!("  {}: {}\n", rules.name(*shape), score));
+        }
+        table.push_str("Cumulative score:\n");
+        for (idx, score) in self.cumulative_scores.iter().enumerate() {
+            table.push_str(&format!("  {}: {}\n", idx + 1, score));
+        }
+        table
+    }
+
+    pub fn to_json(&self, rules: &Rules) -> String {
+        let score_by_shape = self
+            .score_by_shape
+            .iter()
+            .map(|(shape, score)| format!("{}:{}", json_string(rules.name(*shape)), score))
+            .join(",");
+        format!(
+            "{{\"rounds\":{},\"wins\":{},\"ties\":{},\"losses\":{},\"longest_winning_streak\":{},\"total_score\":{},\"score_by_shape\":{{{}}},\"cumulative_scores\":[{}]}}",
+            self.cumulative_scores.len(),
+            self.wins,
+            self.ties,
+            self.losses,
+            self.longest_winning_streak,
+            self.total_score(),
+            score_by_shape,
+            self.cumulative_scores.iter().join(",")
+        )
+    }
 }
 
-pub fn part_two(input: &str) -> String {
-    input
-        .lines()
-        .map(Plan::from_str)
-        .map(Result::unwrap)
-        .map(Game::from)
-        .map(|game| game.score())
-        .sum::<i32>()
-        .to_string()
+fn json_string(s: &str) -> String {
+    let mut json = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
 }
 
 #[cfg(test)]
@@ -192,28 +638,223 @@ mod tests {
     #[test]
     fn test_part_one_example() {
         let example = include_str!("../../inputs/2/1/example.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "15");
+        let answer = (DAY_TWO.part_one)(example);
+        assert_eq!(answer, Answer::Integer(15));
     }
 
     #[test]
     fn test_part_one_input() {
         let example = include_str!("../../inputs/2/1/input.txt");
-        let answer = part_one(example);
-        assert_eq!(answer, "13446");
+        let answer = (DAY_TWO.part_one)(example);
+        assert_eq!(answer, Answer::Integer(13446));
     }
 
     #[test]
     fn test_part_two_example() {
         let example = include_str!("../../inputs/2/2/example.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "12");
+        let answer = (DAY_TWO.part_two)(example);
+        assert_eq!(answer, Answer::Integer(12));
     }
 
     #[test]
     fn test_part_two_input() {
         let example = include_str!("../../inputs/2/2/input.txt");
-        let answer = part_two(example);
-        assert_eq!(answer, "13509");
+        let answer = (DAY_TWO.part_two)(example);
+        assert_eq!(answer, Answer::Integer(13509));
+    }
+
+    #[test]
+    fn test_whitespace_between_columns() {
+        let spaced = "A\tY\nB   X\nC \t Z\n";
+        assert_eq!((DAY_TWO.part_one)(spaced), Answer::Integer(15));
+        assert_eq!((DAY_TWO.part_two)(spaced), Answer::Integer(12));
+    }
+
+    #[test]
+    fn test_rock_paper_scissors_preset() {
+        let rules = Rules::rock_paper_scissors();
+        let (rock, paper, scissors) = (Shape::ROCK, Shape::PAPER, Shape::SCISSORS);
+        assert_eq!(rules.outcome(paper, rock), Outcome::Win);
+        assert_eq!(rules.outcome(rock, scissors), Outcome::Win);
+        assert_eq!(rules.outcome(scissors, rock), Outcome::Lose);
+        assert_eq!(rules.outcome(paper, paper), Outcome::Tie);
+        assert_eq!(rules.shape_for(rock, Outcome::Win), paper);
+        assert_eq!(rules.shape_for(rock, Outcome::Lose), scissors);
+        assert_eq!(rules.shape_for(rock, Outcome::Tie), rock);
+    }
+
+    #[test]
+    fn test_rock_paper_scissors_lizard_spock() {
+        let rules = Rules::rock_paper_scissors_lizard_spock();
+        let shape = |name| rules.shapes().find(|&s| rules.name(s) == name).unwrap();
+        let beats = [
+            ("Scissors", "Paper"),
+            ("Paper", "Rock"),
+            ("Rock", "Lizard"),
+            ("Lizard", "Spock"),
+            ("Spock", "Scissors"),
+            ("Scissors", "Lizard"),
+            ("Lizard", "Paper"),
+            ("Paper", "Spock"),
+            ("Spock", "Rock"),
+            ("Rock", "Scissors"),
+        ];
+        for (winner, loser) in beats {
+            assert_eq!(rules.outcome(shape(winner), shape(loser)), Outcome::Win);
+            assert_eq!(rules.outcome(shape(loser), shape(winner)), Outcome::Lose);
+        }
+        // Both Paper and Spock beat Rock; Paper scores more.
+        assert_eq!(rules.shape_for(shape("Rock"), Outcome::Win), shape("Paper"));
+    }
+
+    #[test]
+    fn test_cyclic_rules() {
+        let scores = OutcomeScores {
+            win: 1,
+            tie: 0,
+            lose: -1,
+        };
+        assert!(Rules::cyclic(&[("a", 1), ("b", 1)], scores).is_err());
+        assert!(Rules::cyclic(&[("a", 1)], scores).is_err());
+        assert!(Rules::cyclic(&[], scores).is_err());
+
+        let shapes = ["a", "b", "c", "d", "e", "f", "g"].map(|name| (name, 0));
+        let rules = Rules::cyclic(&shapes, scores).unwrap();
+        for mine in rules.shapes() {
+            let wins = rules
+                .shapes()
+                .filter(|&theirs| rules.outcome(mine, theirs) == Outcome::Win)
+                .count();
+            assert_eq!(wins, 3);
+        }
+        let game = Game {
+            their_move: Shape(0),
+            my_move: Shape(6),
+        };
+        assert_eq!(game.outcome(&rules), Outcome::Lose);
+        assert_eq!(game.score(&rules), -1);
+    }
+
+    #[test]
+    fn test_encodings() {
+        let rules = Rules::rock_paper_scissors();
+        let example = include_str!("../../inputs/2/1/example.txt");
+        let score =
+            |encoding: &Encoding| total_score(&parse_rounds(example, encoding).unwrap(), &rules);
+        assert_eq!(score(&Encoding::shapes(&rules)), 15);
+        assert_eq!(score(&Encoding::outcomes(&rules)), 12);
+
+        let text = "\
+# The same guide, written with a different alphabet.
+their A = rock
+their B = paper
+their C = scissors
+my X = Lose
+my Y = draw  # Ties are draws
+my Z = WIN
+";
+        assert_eq!(score(&Encoding::parse(text, &rules).unwrap()), 12);
+
+        let mut encoding = Encoding::shapes(&rules);
+        encoding
+            .set_theirs("1=Rock,2=Paper,3=Scissors", &rules)
+            .unwrap();
+        encoding
+            .set_mine("r=Rock, p=Paper, s=Scissors, w=win", &rules)
+            .unwrap();
+        let rounds = parse_rounds("1 p\n2 r\n3 w", &encoding).unwrap();
+        assert_eq!(total_score(&rounds, &rules), 8 + 1 + 7);
+
+        assert!(encoding.parse_round("A X").is_err());
+        assert!(Encoding::parse("their A = Rock\ntheir A = Paper", &rules).is_err());
+        assert!(Encoding::parse("my X = Spock", &rules).is_err());
+        assert!(Encoding::parse("yours X = Rock", &rules).is_err());
+    }
+
+    #[test]
+    fn test_default_encoding_follows_rules() {
+        let rules = Rules::rock_paper_scissors_lizard_spock();
+        let example = include_str!("../../inputs/2/1/example.txt");
+        let score =
+            |encoding: &Encoding| total_score(&parse_rounds(example, encoding).unwrap(), &rules);
+
+        let mut explicit = Encoding::default();
+        explicit
+            .set_theirs("A=Rock,B=Paper,C=Scissors", &rules)
+            .unwrap();
+        explicit
+            .set_mine("X=Rock,Y=Paper,Z=Scissors", &rules)
+            .unwrap();
+        assert_eq!(score(&Encoding::shapes(&rules)), score(&explicit));
+        assert_eq!(score(&explicit), 18);
+    }
+
+    #[test]
+    fn test_decryptions() {
+        let rules = Rules::rock_paper_scissors();
+        let example = include_str!("../../inputs/2/1/example.txt");
+        let decryptions = decryptions(example, &Encoding::shapes(&rules), &rules).unwrap();
+        assert_eq!(decryptions.len(), 12);
+
+        let best = &decryptions[0];
+        assert!(decryptions.iter().all(|d| d.score <= best.score));
+        assert_eq!(best.describe(&rules), "X=Scissors Y=Paper Z=Rock");
+        assert_eq!(best.score, 24);
+
+        let as_guide = decryptions
+            .iter()
+            .find(|d| d.describe(&rules) == "X=Rock Y=Paper Z=Scissors")
+            .unwrap();
+        assert_eq!(as_guide.score, 15);
+        let as_outcomes = decryptions
+            .iter()
+            .find(|d| d.describe(&rules) == "X=Lose Y=Tie Z=Win")
+            .unwrap();
+        assert_eq!(as_outcomes.score, 12);
+
+        let four_symbols = format!("{}\nC W\n", example.trim_end());
+        let error =
+            super::decryptions(&four_symbols, &Encoding::shapes(&rules), &rules).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The second column has 4 distinct symbols, more than the 3 shapes"
+        );
+    }
+
+    #[test]
+    fn test_round_table() {
+        let rules = Rules::rock_paper_scissors();
+        let rounds = parse_rounds("A Y\nB X", &Encoding::shapes(&rules)).unwrap();
+        assert_eq!(
+            round_table(&rounds, &rules),
+            "round\ttheirs\tmine\toutcome\tshape score\toutcome score\tscore\n\
+             1\tRock\tPaper\tWin\t2\t6\t8\n\
+             2\tPaper\tRock\tLose\t1\t0\t1\n"
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let rules = Rules::rock_paper_scissors();
+        let rounds = parse_rounds("A Y\nB Z\nC Z\nA Z\nA X", &Encoding::shapes(&rules)).unwrap();
+        let report = Report::new(&rounds, &rules);
+        assert_eq!((report.wins, report.ties, report.losses), (2, 2, 1));
+        assert_eq!(report.longest_winning_streak, 2);
+        assert_eq!(report.cumulative_scores, vec![8, 17, 23, 26, 30]);
+        assert_eq!(
+            report.score_by_shape,
+            vec![(Shape::ROCK, 4), (Shape::PAPER, 8), (Shape::SCISSORS, 18)]
+        );
+        assert_eq!(
+            report.to_json(&rules),
+            "{\"rounds\":5,\"wins\":2,\"ties\":2,\"losses\":1,\"longest_winning_streak\":2,\
+             \"total_score\":30,\"score_by_shape\":{\"Rock\":4,\"Paper\":8,\"Scissors\":18},\
+             \"cumulative_scores\":[8,17,23,26,30]}"
+        );
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+
+        let empty = Report::new(&[], &rules);
+        assert_eq!(empty.total_score(), 0);
+        assert!(empty.to_table(&rules).starts_with("Rounds: 0\n"));
     }
 }
@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+
+use itertools::Itertools;
+
+/// How much trailing whitespace `normalise` removes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Trim {
+    /// Keep all whitespace.
+    None,
+    /// Remove whitespace, including blank lines, at the end of the input.
+    End,
+    /// Remove whitespace at the end of every line as well as of the input.
+    #[default]
+    Lines,
+}
+
+/// Removes a byte order mark, turns CRLF line endings into LF and trims
+/// trailing whitespace. Borrows the input if nothing needs to change.
+pub fn normalise(input: &str, trim: Trim) -> Cow<'_, str> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let input = if input.contains("\r\n") {
+        Cow::Owned(input.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(input)
+    };
+
+    if trim == Trim::None {
+        return input;
+    }
+    let input = match trim {
+        Trim::Lines if input.lines().any(|line| line != line.trim_end()) => {
+            Cow::Owned(input.lines().map(str::trim_end).join("\n"))
+        }
+        _ => input,
+    };
+    match input {
+        Cow::Borrowed(input) => Cow::Borrowed(input.trim_end()),
+        Cow::Owned(mut input) => {
+            input.truncate(input.trim_end().len());
+            Cow::Owned(input)
+        }
+    }
+}
+
+/// Splits the input at runs of blank lines, yielding each section with the
+/// line number of its first line. Blank lines before the first section and
+/// after the last are ignored.
+pub fn sections(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut lines = input.split_inclusive('\n').enumerate().peekable();
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        // Skip blank lines up to the start of the next section.
+        let (first_idx, start) = loop {
+            let (idx, line) = lines.next()?;
+            offset += line.len();
+            if !line.trim().is_empty() {
+                break (idx, offset - line.len());
+            }
+        };
+        let mut end = offset;
+        while let Some((_, line)) = lines.next_if(|(_, line)| !line.trim().is_empty()) {
+            offset += line.len();
+            end = offset;
+        }
+
+        let section = input[start..end].trim_end_matches(['\r', '\n']);
+        Some((first_idx + 1, section))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+
+    #[test]
+    fn test_normalise() {
+        assert!(matches!(
+            normalise("1\n2", Trim::Lines),
+            Cow::Borrowed("1\n2")
+        ));
+        assert_eq!(
+            normalise("\u{feff}1\r\n2 \r\n\r\n", Trim::None),
+            "1\n2 \n\n"
+        );
+        assert_eq!(normalise("\u{feff}1\r\n2 \r\n\r\n", Trim::End), "1\n2");
+        assert_eq!(normalise(" 1 \n\t\n 2\t\n\n", Trim::End), " 1 \n\t\n 2");
+        assert_eq!(normalise(" 1 \n\t\n 2\t\n\n", Trim::Lines), " 1\n\n 2");
+    }
+
+    #[test]
+    fn test_sections() {
+        assert_eq!(
+            sections("\n1\n2\n\n \n3\r\n\r\n4").collect::<Vec<_>>(),
+            vec![(2, "1\n2"), (6, "3"), (8, "4")]
+        );
+        assert_eq!(
+            sections("    [D]\n 1 \n\nmove\n").collect::<Vec<_>>(),
+            vec![(1, "    [D]\n 1 "), (4, "move")]
+        );
+        assert_eq!(sections("\n \n").count(), 0);
+    }
+}
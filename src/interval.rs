@@ -0,0 +1,296 @@
+use std::fmt::{Debug, Display};
+
+/// Primitive integer types that intervals can be built from.
+///
+/// Arithmetic on bounds goes through `i128` so that lengths and neighbouring
+/// values never overflow the underlying type.
+pub trait Integer: Copy + Ord + Debug {
+    fn to_i128(self) -> i128;
+
+    /// Only called with values between two existing values of `Self`.
+    fn from_i128(value: i128) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                fn to_i128(self) -> i128 {
+                    self as i128
+                }
+
+                fn from_i128(value: i128) -> Self {
+                    value as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// A non-empty, inclusive range of integers `start..=end`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Integer> Interval<T> {
+    /// Returns `None` if `start > end`.
+    pub fn new(start: T, end: T) -> Option<Self> {
+        (start <= end).then_some(Interval { start, end })
+    }
+
+    pub fn point(value: T) -> Self {
+        Interval {
+            start: value,
+            end: value,
+        }
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    pub fn end(&self) -> T {
+        self.end
+    }
+
+    /// The number of integers in the interval.
+    #[allow(clippy::len_without_is_empty)] // Intervals are never empty.
+    pub fn len(&self) -> u128 {
+        (self.end.to_i128() - self.start.to_i128() + 1) as u128
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.start <= *value && *value <= self.end
+    }
+
+    /// Whether every value of `other` is also in `self`.
+    pub fn is_superset(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// Whether the two intervals overlap or touch, e.g. `1..=3` and `4..=6`.
+    pub fn is_adjacent_or_overlapping(&self, other: &Self) -> bool {
+        self.start.to_i128() <= other.end.to_i128() + 1
+            && other.start.to_i128() <= self.end.to_i128() + 1
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        Interval::new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    /// The smallest interval covering both, if that covers nothing else.
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        self.is_adjacent_or_overlapping(other).then(|| Interval {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        })
+    }
+
+    /// The values of `self` that are not in `other`, as up to two intervals.
+    pub fn difference(&self, other: &Self) -> Vec<Self> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+
+        let mut remaining = Vec::new();
+        if self.start < other.start {
+            remaining.push(Interval {
+                start: self.start,
+                end: T::from_i128(other.start.to_i128() - 1),
+            });
+        }
+        if other.end < self.end {
+            remaining.push(Interval {
+                start: T::from_i128(other.end.to_i128() + 1),
+                end: self.end,
+            });
+        }
+        remaining
+    }
+}
+
+impl<T: Display> Display for Interval<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..={}", self.start, self.end)
+    }
+}
+
+/// Merges intervals into the sorted, disjoint list covering the same values.
+/// Touching intervals are joined.
+pub fn merge<T: Integer>(intervals: impl IntoIterator<Item = Interval<T>>) -> Vec<Interval<T>> {
+    let mut intervals = intervals.into_iter().collect::<Vec<_>>();
+    intervals.sort_unstable();
+
+    let mut merged: Vec<Interval<T>> = Vec::with_capacity(intervals.len());
+    for interval in intervals {
+        match merged.last_mut().and_then(|last| last.union(&interval)) {
+            Some(union) => *merged.last_mut().unwrap() = union,
+            None => merged.push(interval),
+        }
+    }
+    merged
+}
+
+/// A set of integers stored as sorted, disjoint, non-touching intervals.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet {
+            intervals: Vec::new(),
+        }
+    }
+}
+
+impl<T: Integer> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        let first = self
+            .intervals
+            .partition_point(|i| i.end.to_i128() + 1 < interval.start.to_i128());
+        let last = self
+            .intervals
+            .partition_point(|i| i.start.to_i128() <= interval.end.to_i128() + 1);
+
+        let merged = self.intervals[first..last]
+            .iter()
+            .fold(interval, |acc, i| acc.union(i).unwrap());
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn remove(&mut self, interval: Interval<T>) {
+        let first = self.intervals.partition_point(|i| i.end < interval.start);
+        let last = self.intervals.partition_point(|i| i.start <= interval.end);
+
+        let remaining = self.intervals[first..last]
+            .iter()
+            .flat_map(|i| i.difference(&interval))
+            .collect::<Vec<_>>();
+        self.intervals.splice(first..last, remaining);
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        let idx = self.intervals.partition_point(|i| i.end < *value);
+        self.intervals
+            .get(idx)
+            .is_some_and(|interval| interval.contains(value))
+    }
+
+    /// The number of integers in the set.
+    pub fn len(&self) -> u128 {
+        self.intervals.iter().map(Interval::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval<T>> {
+        self.intervals.iter()
+    }
+}
+
+impl<T: Integer> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        IntervalSet {
+            intervals: merge(iter),
+        }
+    }
+}
+
+impl<T: Integer> Extend<Interval<T>> for IntervalSet<T> {
+    fn extend<I: IntoIterator<Item = Interval<T>>>(&mut self, iter: I) {
+        for interval in iter {
+            self.insert(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+
+    fn i(start: i32, end: i32) -> Interval<i32> {
+        Interval::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_interval_operations() {
+        assert_eq!(Interval::new(3, 2), None);
+        assert_eq!(i(2, 8).len(), 7);
+        assert_eq!(Interval::new(u64::MIN, u64::MAX).unwrap().len(), 1 << 64);
+        assert!(i(2, 8).is_superset(&i(3, 7)));
+        assert!(!i(3, 7).is_superset(&i(2, 8)));
+        assert!(i(5, 7).overlaps(&i(7, 9)));
+        assert!(!i(2, 4).overlaps(&i(6, 8)));
+        assert_eq!(i(5, 7).intersection(&i(7, 9)), Some(i(7, 7)));
+        assert_eq!(i(2, 4).intersection(&i(6, 8)), None);
+        assert_eq!(i(1, 3).union(&i(4, 6)), Some(i(1, 6)));
+        assert_eq!(i(1, 3).union(&i(5, 6)), None);
+        assert_eq!(i(1, 10).difference(&i(3, 5)), vec![i(1, 2), i(6, 10)]);
+        assert_eq!(i(1, 10).difference(&i(0, 5)), vec![i(6, 10)]);
+        assert_eq!(i(1, 10).difference(&i(0, 11)), vec![]);
+        assert_eq!(
+            Interval::new(u8::MIN, u8::MAX)
+                .unwrap()
+                .difference(&Interval::point(0)),
+            vec![Interval::new(1, 255).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        assert_eq!(
+            merge([i(5, 7), i(1, 2), i(3, 3), i(10, 12), i(11, 20)]),
+            vec![i(1, 3), i(5, 7), i(10, 20)]
+        );
+        assert_eq!(merge(Vec::<Interval<i32>>::new()), vec![]);
+    }
+
+    #[test]
+    fn test_interval_set() {
+        let mut set = IntervalSet::new();
+        set.insert(i(1, 3));
+        set.insert(i(10, 12));
+        set.insert(i(5, 6));
+        set.insert(i(4, 4));
+        assert_eq!(
+            set.iter().copied().collect::<Vec<_>>(),
+            vec![i(1, 6), i(10, 12)]
+        );
+        assert_eq!(set.len(), 9);
+
+        set.remove(i(2, 11));
+        assert_eq!(
+            set.iter().copied().collect::<Vec<_>>(),
+            vec![i(1, 1), i(12, 12)]
+        );
+        assert!(set.contains(&12));
+        assert!(!set.contains(&5));
+
+        set.remove(i(0, 20));
+        assert!(set.is_empty());
+
+        let collected = [i(3, 4), i(1, 2), i(8, 9)]
+            .into_iter()
+            .collect::<IntervalSet<_>>();
+        assert_eq!(
+            collected.iter().copied().collect::<Vec<_>>(),
+            vec![i(1, 4), i(8, 9)]
+        );
+    }
+}
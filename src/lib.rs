@@ -1 +1,6 @@
+pub mod answer;
 pub mod days;
+pub mod input;
+pub mod interval;
+pub mod parse;
+pub mod random;
@@ -1,27 +1,244 @@
+use std::fs::File;
+
 use anyhow::{bail, Context, Result};
-use aoc_2022::days::DAYS;
-use clap::Parser;
+use aoc_2022::{
+    answer::Answer,
+    days::{
+        four::{parse_assignments, InvertedRanges, Roster},
+        one::{par_top_k, Inventory},
+        six::MarkerReport,
+        two::{decryptions, parse_rounds, round_table, total_score, Encoding, Report, Rules},
+        Day, RunOptions, DAYS,
+    },
+    random::Rng,
+};
+use clap::{Parser, Subcommand};
 
 /// A CLI tool that solves Advent of Code 2022 puzzles
 #[derive(Debug, Parser)]
 #[command(author, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The day of the puzzle to solve
-    #[arg(short, long)]
-    day: u8,
+    #[arg(short, long, required = true)]
+    day: Option<u8>,
 
-    /// The part of the puzzle to solve
+    /// The part of the puzzle to solve, solves both parts from one parse of the input if not provided
     #[arg(short, long)]
-    part: u8,
+    part: Option<u8>,
 
     /// The input to use for the puzzle
     /// If not provided, the inputs directory will be searched for a file matching the day and part
     #[arg(short, long)]
     input: Option<String>,
+
+    /// Compare the answer with the one stored next to the input, in a file with an `answer` extension
+    #[arg(short, long, requires = "part")]
+    check: bool,
+
+    /// Report how long parsing the input and solving each part took
+    #[arg(short, long)]
+    time: bool,
+
+    /// Skip invalid input lines and solve with the rest instead of failing
+    #[arg(short, long)]
+    lenient: bool,
+
+    /// How many invalid lines to describe, the rest are only counted
+    #[arg(long, default_value_t = 10)]
+    max_errors: usize,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Find the elves carrying the most calories in a day 1 inventory, reading it in parallel
+    Top {
+        /// How many elves to find
+        #[arg(short, long, default_value_t = 3)]
+        count: usize,
+
+        /// The number of threads to sum chunks of the inventory on, defaults to one per CPU
+        #[arg(short, long)]
+        threads: Option<usize>,
+
+        /// The inventory to read, defaults to the day 1 example
+        #[arg(short, long)]
+        input: Option<String>,
+    },
+
+    /// Summarise a day 1 inventory and query its elves
+    Inventory {
+        /// List the elves carrying more than this many calories
+        #[arg(short, long)]
+        above: Option<u64>,
+
+        /// Print where this elf, numbered from 1, ranks by calories carried
+        #[arg(short, long)]
+        rank: Option<usize>,
+
+        /// The inventory to query, defaults to the day 1 example
+        #[arg(short, long)]
+        input: Option<String>,
+    },
+
+    /// Report every marker of a given window size in a day 6 datastream
+    Markers {
+        /// The number of distinct characters that make up a marker
+        #[arg(short, long, default_value_t = 4)]
+        window: usize,
+
+        /// Print the position of every marker
+        #[arg(short, long)]
+        list: bool,
+
+        /// The datastream to analyse, defaults to the day 6 example
+        #[arg(short, long)]
+        input: Option<String>,
+    },
+
+    /// Analyse the coverage of every day 4 section assignment together
+    Roster {
+        /// Read ranges written back to front, like 9-3, as 3-9 instead of rejecting them
+        #[arg(short, long)]
+        normalise: bool,
+
+        /// Print the line number of every redundant pair
+        #[arg(short, long)]
+        list: bool,
+
+        /// The assignments to analyse, defaults to the day 4 example
+        #[arg(short, long)]
+        input: Option<String>,
+    },
+
+    /// Score a day 2 strategy guide written with any symbols
+    Score {
+        /// A file of `their SYMBOL = SHAPE` and `my SYMBOL = SHAPE|OUTCOME` lines,
+        /// defaults to reading both columns as shapes like part one
+        #[arg(short, long)]
+        encoding: Option<String>,
+
+        /// Comma-separated SYMBOL=SHAPE entries replacing the first column
+        #[arg(long)]
+        their: Option<String>,
+
+        /// Comma-separated SYMBOL=SHAPE or SYMBOL=OUTCOME entries replacing the second column
+        #[arg(long)]
+        my: Option<String>,
+
+        /// Play rock, paper, scissors, lizard, Spock instead
+        #[arg(long)]
+        rpsls: bool,
+
+        /// The strategy guide to score, defaults to the day 2 example
+        #[arg(short, long)]
+        input: Option<String>,
+    },
+
+    /// Print win, tie and loss counts, per-shape scores and running totals for a day 2 strategy guide
+    Report {
+        /// An encoding file, see `score`
+        #[arg(short, long)]
+        encoding: Option<String>,
+
+        /// Comma-separated SYMBOL=SHAPE entries replacing the first column
+        #[arg(long)]
+        their: Option<String>,
+
+        /// Comma-separated SYMBOL=SHAPE or SYMBOL=OUTCOME entries replacing the second column
+        #[arg(long)]
+        my: Option<String>,
+
+        /// Play rock, paper, scissors, lizard, Spock instead
+        #[arg(long)]
+        rpsls: bool,
+
+        /// Print the report as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+
+        /// The strategy guide to report on, defaults to the day 2 example
+        #[arg(short, long)]
+        input: Option<String>,
+    },
+
+    /// Find the highest scoring reading of the second column of a day 2 strategy guide
+    Decrypt {
+        /// An encoding file for the first column, see `score`
+        #[arg(short, long)]
+        encoding: Option<String>,
+
+        /// Comma-separated SYMBOL=SHAPE entries for the first column
+        #[arg(long)]
+        their: Option<String>,
+
+        /// Play rock, paper, scissors, lizard, Spock instead
+        #[arg(long)]
+        rpsls: bool,
+
+        /// Print a per-round table for the best reading
+        #[arg(short, long)]
+        rounds: bool,
+
+        /// The strategy guide to decrypt, defaults to the day 2 example
+        #[arg(short, long)]
+        input: Option<String>,
+    },
+    /// Print a random input for a day, the same one every time for a given seed
+    Generate {
+        /// The day to generate an input for
+        #[arg(short, long)]
+        day: u8,
+
+        /// The seed of the random number generator
+        #[arg(short, long, default_value_t = 0)]
+        seed: u64,
+
+        /// How big to make the input, such as the number of lines or elves, depending on the day
+        #[arg(long, default_value_t = 100)]
+        size: usize,
+    },
+}
+
+fn load_rules(rpsls: bool) -> Rules {
+    if rpsls {
+        Rules::rock_paper_scissors_lizard_spock()
+    } else {
+        Rules::rock_paper_scissors()
+    }
+}
+
+fn load_encoding(
+    encoding: Option<String>,
+    their: Option<String>,
+    my: Option<String>,
+    rules: &Rules,
+) -> Result<Encoding> {
+    let mut encoding = match encoding {
+        Some(file_path) => {
+            let text = std::fs::read_to_string(file_path).context("Failed to load encoding")?;
+            Encoding::parse(&text, rules).context("Invalid encoding")?
+        }
+        None => Encoding::shapes(rules),
+    };
+    if let Some(their) = their {
+        encoding.set_theirs(&their, rules)?;
+    }
+    if let Some(my) = my {
+        encoding.set_mine(&my, rules)?;
+    }
+    Ok(encoding)
+}
+
+fn example_path(day: u8, part: u8) -> String {
+    format!("inputs/{}/{}/example.txt", day, part)
 }
 
 pub fn load_example_input(day: u8, part: u8) -> Result<String> {
-    let path = format!("inputs/{}/{}/example.txt", day, part);
+    let path = example_path(day, part);
     let input = std::fs::read_to_string(path).context(format!(
         "Failed to load example input for day {} part {}",
         day, part
@@ -29,32 +246,223 @@ pub fn load_example_input(day: u8, part: u8) -> Result<String> {
     Ok(input)
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn load_input(input: Option<String>, day: u8, part: u8) -> Result<String> {
+    match input {
+        Some(file_path) => {
+            std::fs::read_to_string(file_path).context("Failed to load input from file")
+        }
+        None => load_example_input(day, part),
+    }
+}
 
-    if args.part > 2 {
-        bail!("Invalid part number: {}", args.part);
+fn find_day(day: u8) -> Result<&'static Day> {
+    let day_idx = day.checked_sub(1).context("Days start at 1")?;
+    DAYS.get(day_idx as usize)
+        .with_context(|| format!("Day {} is not implemented", day))
+}
+
+fn run_command(command: Command) -> Result<()> {
+    match command {
+        Command::Top {
+            count,
+            threads,
+            input,
+        } => {
+            let threads = match threads {
+                Some(threads) => threads,
+                None => std::thread::available_parallelism().map_or(1, usize::from),
+            };
+            let elves = match input {
+                Some(file_path) => {
+                    let file = File::open(file_path).context("Failed to load input from file")?;
+                    par_top_k(file, count, threads)?
+                }
+                None => par_top_k(load_example_input(1, 1)?.as_bytes(), count, threads)?,
+            };
+            for elf in elves.iter() {
+                println!("Elf {}: {}", elf.index + 1, elf.calories);
+            }
+            let total = elves
+                .iter()
+                .try_fold(0u64, |total, elf| total.checked_add(elf.calories));
+            match total {
+                Some(total) => println!("Total: {}", total),
+                None => println!("Total: more than {}", u64::MAX),
+            }
+        }
+        Command::Inventory { above, rank, input } => {
+            let input = load_input(input, 1, 1)?;
+            let inventory = Inventory::parse(&input)?;
+            println!("{}", inventory);
+            if let Some(threshold) = above {
+                println!("Elves above {} calories:", threshold);
+                for elf in inventory.above(threshold) {
+                    println!("  Elf {}: {}", elf.index + 1, elf.calories());
+                }
+            }
+            if let Some(elf) = rank {
+                let rank = elf
+                    .checked_sub(1)
+                    .and_then(|index| inventory.rank(index))
+                    .with_context(|| format!("There is no elf {}", elf))?;
+                println!("Elf {} ranks {} of {}", elf, rank, inventory.elves.len());
+            }
+        }
+        Command::Markers {
+            window,
+            list,
+            input,
+        } => {
+            let input = load_input(input, 6, 1)?;
+            let report = MarkerReport::new(input.trim_end().as_bytes(), window);
+            println!("{}", report);
+            if list {
+                for marker in report.markers {
+                    println!("{}", marker);
+                }
+            }
+        }
+        Command::Roster {
+            normalise,
+            list,
+            input,
+        } => {
+            let input = load_input(input, 4, 1)?;
+            let inverted = if normalise {
+                InvertedRanges::Normalise
+            } else {
+                InvertedRanges::Reject
+            };
+            let roster = Roster::new(parse_assignments(&input, inverted)?);
+            println!("{}", roster);
+            if list {
+                for idx in roster.redundant_pairs() {
+                    println!("{}", idx + 1);
+                }
+            }
+        }
+        Command::Score {
+            encoding,
+            their,
+            my,
+            rpsls,
+            input,
+        } => {
+            let input = load_input(input, 2, 1)?;
+            let rules = load_rules(rpsls);
+            let encoding = load_encoding(encoding, their, my, &rules)?;
+            let rounds = parse_rounds(&input, &encoding)?;
+            println!("{}", total_score(&rounds, &rules));
+        }
+        Command::Report {
+            encoding,
+            their,
+            my,
+            rpsls,
+            json,
+            input,
+        } => {
+            let input = load_input(input, 2, 1)?;
+            let rules = load_rules(rpsls);
+            let encoding = load_encoding(encoding, their, my, &rules)?;
+            let report = Report::new(&parse_rounds(&input, &encoding)?, &rules);
+            if json {
+                println!("{}", report.to_json(&rules));
+            } else {
+                print!("{}", report.to_table(&rules));
+            }
+        }
+        Command::Decrypt {
+            encoding,
+            their,
+            rpsls,
+            rounds,
+            input,
+        } => {
+            let input = load_input(input, 2, 1)?;
+            let rules = load_rules(rpsls);
+            let encoding = load_encoding(encoding, their, None, &rules)?;
+            let decryptions = decryptions(&input, &encoding, &rules)?;
+            for decryption in decryptions.iter() {
+                println!("{}\t{}", decryption.score, decryption.describe(&rules));
+            }
+            if let (true, Some(best)) = (rounds, decryptions.first()) {
+                let rounds = parse_rounds(&input, &encoding.with_mine(best.mine.clone()))?;
+                print!("\n{}", round_table(&rounds, &rules));
+            }
+        }
+        Command::Generate { day, seed, size } => {
+            let day = find_day(day)?;
+            print!("{}", (day.generate)(&mut Rng::new(seed), size));
+        }
     }
+    Ok(())
+}
 
-    let day_idx = args.day - 1;
+fn main() -> Result<()> {
+    let args = Args::parse();
 
-    if day_idx as usize >= DAYS.len() {
-        bail!("Day {} is not implemented", args.day);
+    if let Some(command) = args.command {
+        return run_command(command);
     }
 
-    let input = match args.input {
-        Some(file_path) => {
-            std::fs::read_to_string(file_path).context("Failed to load input from file")?
+    let day_number = args.day.context("No day given")?;
+    let day = find_day(day_number)?;
+    let parts: &[u8] = match args.part {
+        Some(1) => &[1],
+        Some(2) => &[2],
+        None => day.parts,
+        Some(part) => bail!("Invalid part number: {}", part),
+    };
+    if let Some(unsolved) = parts.iter().find(|part| !day.parts.contains(part)) {
+        bail!("Day {} part {} is not solved yet", day_number, unsolved);
+    }
+    if args.part.is_none() {
+        for unsolved in [1, 2].iter().filter(|part| !day.parts.contains(part)) {
+            eprintln!("note: part {} is not solved yet", unsolved);
         }
-        None => load_example_input(args.day, args.part)?,
+    }
+    let part = parts[0];
+
+    let expected_path = Answer::expected_path(match &args.input {
+        Some(file_path) => file_path.clone(),
+        None => example_path(day_number, part),
+    });
+    let input = load_input(args.input, day_number, part)?;
+
+    let options = RunOptions {
+        lenient: args.lenient,
+        max_errors: args.max_errors,
     };
+    let run = (day.run)(&input, parts, options)?;
+    if !run.skipped.is_empty() {
+        eprintln!(
+            "warning: skipped {} invalid line(s):\n{}",
+            run.skipped.len(),
+            run.skipped
+        );
+    }
+    for (part, answer, _) in run.parts.iter() {
+        if parts.len() == 1 {
+            println!("{}", answer);
+        } else {
+            println!("Part {}: {}", part, answer);
+        }
+    }
 
-    let day = &DAYS[day_idx as usize];
+    if args.time {
+        println!("Parse time: {:.2?}", run.parse_time);
+        for (part, _, solve_time) in run.parts.iter() {
+            println!("Part {} time: {:.2?}", part, solve_time);
+        }
+    }
 
-    if args.part == 1 {
-        println!("{}", (day.part_one)(&input));
-    } else {
-        println!("{}", (day.part_two)(&input));
+    if args.check {
+        let expected = Answer::load(expected_path)?;
+        if run.parts[0].1 != expected {
+            bail!("Incorrect answer, expected {}", expected);
+        }
+        println!("Correct");
     }
 
     Ok(())
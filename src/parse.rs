@@ -0,0 +1,441 @@
+//! Helpers for the line-oriented formats puzzle inputs are written in.
+//!
+//! Errors name the column they were found at, and `parse_lines` adds the line,
+//! so a bad input reads like `line 3: column 6: invalid usize (x)`. Every
+//! invalid line is reported, not just the first.
+
+use std::{
+    any::type_name,
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// A line format with `{}` placeholders, like `"move {} from {} to {}"`.
+///
+/// The text between placeholders must appear exactly, except that a space
+/// matches any run of whitespace. Each placeholder captures everything up
+/// to the next piece of text, trimmed, and may not be empty. Two placeholders
+/// must not be next to each other.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Template<'a> {
+    pattern: &'a str,
+}
+
+/// The text captured by one placeholder, and the column it starts at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Field<'s> {
+    pub column: usize,
+    pub text: &'s str,
+}
+
+impl<'s> Field<'s> {
+    pub fn parse<T>(&self) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.text.parse().with_context(|| {
+            let name = type_name::<T>().rsplit("::").next().unwrap_or_default();
+            format!("column {}: invalid {} ({})", self.column, name, self.text)
+        })
+    }
+}
+
+/// The column `part`, a slice of `line`, starts at. 1-based, counting
+/// characters rather than bytes.
+fn column(line: &str, part: &str) -> usize {
+    let offset = part.as_ptr() as usize - line.as_ptr() as usize;
+    line[..offset].chars().count() + 1
+}
+
+/// The length of the start of `text` that matches `literal`, where a run of
+/// spaces in `literal` stands for any run of whitespace.
+fn match_literal(text: &str, literal: &str) -> Option<usize> {
+    let mut rest = text;
+    let mut chars = literal.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ' ' {
+            while chars.next_if_eq(&' ').is_some() {}
+            let trimmed = rest.trim_start();
+            if trimmed.len() == rest.len() {
+                return None;
+            }
+            rest = trimmed;
+        } else {
+            rest = rest.strip_prefix(c)?;
+        }
+    }
+    Some(text.len() - rest.len())
+}
+
+/// The byte range of the first match of `literal` in `text`.
+fn find_literal(text: &str, literal: &str) -> Option<(usize, usize)> {
+    text.char_indices()
+        .find_map(|(idx, _)| Some((idx, idx + match_literal(&text[idx..], literal)?)))
+}
+
+impl<'a> Template<'a> {
+    pub const fn new(pattern: &'a str) -> Self {
+        Template { pattern }
+    }
+
+    /// The text captured by each placeholder.
+    pub fn fields<'s>(&self, line: &'s str) -> Result<Vec<Field<'s>>> {
+        let mut literals = self.pattern.split("{}");
+        let first = literals.next().unwrap_or_default();
+        let mut rest = match_literal(line, first)
+            .map(|len| &line[len..])
+            .ok_or_else(|| anyhow!("column 1: expected {:?}", first))?;
+
+        let mut fields = Vec::new();
+        let mut literals = literals.peekable();
+        while let Some(literal) = literals.next() {
+            let last = literals.peek().is_none();
+            let (text, after) = match (literal, last) {
+                ("", true) => (rest, ""),
+                ("", false) => panic!("template ({}) has adjacent placeholders", self.pattern),
+                // The final piece of text must end the line, so look for it there.
+                (_, true) => match rest.char_indices().find(|&(idx, _)| {
+                    match_literal(&rest[idx..], literal) == Some(rest.len() - idx)
+                }) {
+                    Some((idx, _)) => (&rest[..idx], ""),
+                    None => bail!("column {}: expected {:?}", column(line, rest), literal),
+                },
+                (_, false) => match find_literal(rest, literal) {
+                    Some((start, end)) => (&rest[..start], &rest[end..]),
+                    None => bail!("column {}: expected {:?}", column(line, rest), literal),
+                },
+            };
+
+            let trimmed = text.trim_start();
+            let field = Field {
+                column: column(line, trimmed),
+                text: trimmed.trim_end(),
+            };
+            if field.text.is_empty() {
+                bail!("column {}: missing value", field.column);
+            }
+            fields.push(field);
+            rest = after;
+        }
+        Ok(fields)
+    }
+
+    /// Matches the line and parses each placeholder as the matching element of
+    /// a tuple, e.g. `(usize, usize, usize)`.
+    pub fn parse<T: FromFields>(&self, line: &str) -> Result<T> {
+        T::from_fields(&self.fields(line)?)
+    }
+}
+
+/// Types that can be built from the fields of a `Template`.
+pub trait FromFields: Sized {
+    fn from_fields(fields: &[Field]) -> Result<Self>;
+}
+
+macro_rules! impl_from_fields {
+    ($len:literal: $($t:ident $idx:tt),*) => {
+        impl<$($t),*> FromFields for ($($t,)*)
+        where
+            $($t: FromStr, $t::Err: std::error::Error + Send + Sync + 'static),*
+        {
+            fn from_fields(fields: &[Field]) -> Result<Self> {
+                if fields.len() != $len {
+                    bail!("expected {} fields, found {}", $len, fields.len());
+                }
+                Ok(($(fields[$idx].parse::<$t>()?,)*))
+            }
+        }
+    };
+}
+
+impl_from_fields!(1: A 0);
+impl_from_fields!(2: A 0, B 1);
+impl_from_fields!(3: A 0, B 1, C 2);
+impl_from_fields!(4: A 0, B 1, C 2, D 3);
+
+/// Splits the line at the only `separator` in it, trimming both sides.
+pub fn split_pair<'s>(line: &'s str, separator: &str) -> Result<(&'s str, &'s str)> {
+    let (left, right) = line
+        .split_once(separator)
+        .ok_or_else(|| anyhow!("expected {:?}", separator))?;
+    if let Some(idx) = right.find(separator) {
+        let extra = &right[idx..];
+        bail!("column {}: unexpected {:?}", column(line, extra), separator);
+    }
+    Ok((left.trim(), right.trim()))
+}
+
+/// Splits a line into whitespace-separated words, honouring single quotes,
+/// double quotes and backslash escapes so words may contain whitespace.
+pub fn words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.char_indices();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' | '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some((_, quote)) if quote == c => break,
+                        Some((_, '\\')) if c == '"' => {
+                            word.push(chars.next().map_or('\\', |(_, c)| c))
+                        }
+                        Some((_, c)) => word.push(c),
+                        None => bail!(
+                            "column {}: unterminated {} quote",
+                            column(line, &line[idx..]),
+                            c
+                        ),
+                    }
+                }
+            }
+            '\\' => word
+                .get_or_insert_with(String::new)
+                .push(chars.next().map_or('\\', |(_, c)| c)),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+/// The invalid lines of an input, each with why it is invalid.
+///
+/// Only the first `limit` are kept, the rest are just counted.
+#[derive(Debug)]
+pub struct LineErrors {
+    errors: Vec<(usize, anyhow::Error)>,
+    count: usize,
+    limit: usize,
+}
+
+impl Default for LineErrors {
+    fn default() -> Self {
+        LineErrors::new(10)
+    }
+}
+
+impl LineErrors {
+    pub fn new(limit: usize) -> Self {
+        LineErrors {
+            errors: Vec::new(),
+            count: 0,
+            limit,
+        }
+    }
+
+    pub fn push(&mut self, line: usize, error: anyhow::Error) {
+        if self.errors.len() < self.limit {
+            self.errors.push((line, error));
+        }
+        self.count += 1;
+    }
+
+    /// The number of invalid lines, including those beyond the limit.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The kept errors and their line numbers.
+    pub fn iter(&self) -> impl Iterator<Item = &(usize, anyhow::Error)> {
+        self.errors.iter()
+    }
+
+    /// Parses every line with `parse`, recording the invalid lines and
+    /// returning the values of the rest. `first_line` is the line number of the
+    /// first line of `input`.
+    pub fn parse_lines<'a, T>(
+        &mut self,
+        first_line: usize,
+        input: &'a str,
+        mut parse: impl FnMut(&'a str) -> Result<T>,
+    ) -> Vec<T> {
+        input
+            .lines()
+            .enumerate()
+            .filter_map(|(idx, line)| match parse(line) {
+                Ok(value) => Some(value),
+                Err(error) => {
+                    self.push(first_line + idx, error);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Fails with these errors if there are any, otherwise returns `value`.
+    pub fn check<T>(self, value: T) -> Result<T> {
+        if self.is_empty() {
+            Ok(value)
+        } else {
+            Err(self.into())
+        }
+    }
+}
+
+impl Display for LineErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, (line, error)) in self.errors.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "line {}: {:#}", line, error)?;
+        }
+        match self.count - self.errors.len() {
+            0 => Ok(()),
+            1 => write!(f, "\n... and 1 more invalid line"),
+            more => write!(f, "\n... and {} more invalid lines", more),
+        }
+    }
+}
+
+impl std::error::Error for LineErrors {}
+
+/// Parses every line with `parse`, naming every invalid line if there are any.
+pub fn parse_lines<'a, T>(
+    input: &'a str,
+    parse: impl FnMut(&'a str) -> Result<T>,
+) -> Result<Vec<T>> {
+    parse_lines_from(1, input, parse)
+}
+
+/// The same as `parse_lines`, for text whose first line is `first_line` of a
+/// larger input, such as one of its `input::sections`.
+pub fn parse_lines_from<'a, T>(
+    first_line: usize,
+    input: &'a str,
+    parse: impl FnMut(&'a str) -> Result<T>,
+) -> Result<Vec<T>> {
+    let mut errors = LineErrors::default();
+    let values = errors.parse_lines(first_line, input, parse);
+    errors.check(values)
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+
+    fn error<T: Debug>(result: Result<T>) -> String {
+        format!("{:#}", result.unwrap_err())
+    }
+
+    #[test]
+    fn test_template() {
+        let template = Template::new("move {} from {} to {}");
+        assert_eq!(
+            template
+                .parse::<(usize, usize, usize)>("move 3 from 1 to 2")
+                .unwrap(),
+            (3, 1, 2)
+        );
+        assert_eq!(
+            template
+                .parse::<(usize, usize, usize)>("move  13 from 1 to 2 ")
+                .unwrap(),
+            (13, 1, 2)
+        );
+        assert_eq!(
+            error(template.parse::<(usize, usize, usize)>("move x from 1 to 2")),
+            "column 6: invalid usize (x): invalid digit found in string"
+        );
+        assert_eq!(
+            error(template.parse::<(usize, usize, usize)>("move 3 to 2")),
+            "column 6: expected \" from \""
+        );
+        assert_eq!(
+            error(template.parse::<(usize, usize, usize)>("shift 3 from 1 to 2")),
+            "column 1: expected \"move \""
+        );
+        assert_eq!(
+            error(template.parse::<(usize, usize)>("move 3 from 1 to 2")),
+            "expected 2 fields, found 3"
+        );
+
+        let range = Template::new("{}-{},{}-{}");
+        assert_eq!(
+            range.fields("2-4,6-8").unwrap()[2],
+            Field {
+                column: 5,
+                text: "6"
+            }
+        );
+        assert_eq!(error(range.fields("2-4,-8")), "column 5: missing value");
+        assert_eq!(
+            Template::new("[{}]").fields("[a]b]").unwrap()[0].text,
+            "a]b"
+        );
+
+        let round = Template::new("{} {}");
+        for line in ["A Y", "A\tY", "A  \t Y"] {
+            assert_eq!(round.parse::<(char, char)>(line).unwrap(), ('A', 'Y'));
+        }
+        assert_eq!(error(round.fields("AY")), "column 1: expected \" \"");
+        assert_eq!(
+            template
+                .parse::<(usize, usize, usize)>("move\t3\tfrom 1  to\t2")
+                .unwrap(),
+            (3, 1, 2)
+        );
+        assert!(template.fields("move 3 fromto 2").is_err());
+    }
+
+    #[test]
+    fn test_split_pair() {
+        assert_eq!(split_pair("X = Rock", "=").unwrap(), ("X", "Rock"));
+        assert_eq!(error(split_pair("X Rock", "=")), "expected \"=\"");
+        assert_eq!(
+            error(split_pair("X=Y=Z", "=")),
+            "column 4: unexpected \"=\""
+        );
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            words(r#"a 'b c'  "d \"e\"" f\ g"#).unwrap(),
+            vec!["a", "b c", "d \"e\"", "f g"]
+        );
+        assert_eq!(error(words("ab 'cd")), "column 4: unterminated ' quote");
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        assert_eq!(
+            parse_lines("1\n2", |line| Ok(line.parse::<u32>()?)).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            error(parse_lines_from(5, "1\nx", |line| Ok(line.parse::<u32>()?))),
+            "line 6: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn test_line_errors() {
+        let parse = |line: &str| Ok(line.parse::<u32>()?);
+        assert_eq!(
+            error(parse_lines("x\n1\ny", parse)),
+            "line 1: invalid digit found in string\nline 3: invalid digit found in string"
+        );
+
+        let mut errors = LineErrors::new(1);
+        assert_eq!(errors.parse_lines(1, "1\nx\n2\ny\nz", parse), vec![1, 2]);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            errors.to_string(),
+            "line 2: invalid digit found in string\n... and 2 more invalid lines"
+        );
+        assert!(LineErrors::default().check(()).is_ok());
+    }
+}